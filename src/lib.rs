@@ -5,18 +5,28 @@
 extern crate libc;
 
 use std::ascii;
-use std::cmp::Ordering;
+use std::cmp::{self, Ordering};
 use std::convert::AsRef;
+use std::env;
+use std::error;
 use std::ffi::OsStr;
 use std::fmt;
-use std::io;
+use std::fs;
+use std::io::{self, IoSlice, IoSliceMut};
 use std::iter::IntoIterator;
-use std::mem;
+use std::mem::{self, MaybeUninit};
 use std::net::Shutdown;
 use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt};
 use std::os::unix::io::{RawFd, AsRawFd, FromRawFd, IntoRawFd};
-use std::path::Path;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{self, AtomicU64, AtomicUsize};
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 fn sun_path_offset() -> usize {
     unsafe {
@@ -28,6 +38,12 @@ fn sun_path_offset() -> usize {
     }
 }
 
+// The longest `shutdown_after_flush` will wait for the send queue to drain.
+const FLUSH_TIMEOUT_SECS: u64 = 5;
+
+// The backlog passed to `listen` by the listener constructors.
+const LISTEN_BACKLOG: libc::c_int = 128;
+
 fn cvt(v: libc::c_int) -> io::Result<libc::c_int> {
     if v < 0 {
         Err(io::Error::last_os_error())
@@ -44,6 +60,209 @@ fn cvt_s(v: libc::ssize_t) -> io::Result<libc::ssize_t> {
     }
 }
 
+// Request and attribute definitions from linux/sock_diag.h and
+// linux/unix_diag.h
+#[cfg(target_os = "linux")]
+const SOCK_DIAG_BY_FAMILY: u16 = 20;
+#[cfg(target_os = "linux")]
+const UDIAG_SHOW_RQLEN: u32 = 0x10;
+#[cfg(target_os = "linux")]
+const UNIX_DIAG_RQLEN: u16 = 4;
+
+// From linux/kcmp.h
+#[cfg(target_os = "linux")]
+const KCMP_FILE: libc::c_int = 0;
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct UnixDiagRequest {
+    header: libc::nlmsghdr,
+    sdiag_family: u8,
+    sdiag_protocol: u8,
+    pad: u16,
+    udiag_states: u32,
+    udiag_ino: u32,
+    udiag_show: u32,
+    udiag_cookie: [u32; 2],
+}
+
+#[cfg(target_os = "linux")]
+fn read_u16(buf: &[u8], off: usize) -> u16 {
+    let mut bytes = [0; 2];
+    bytes.copy_from_slice(&buf[off..off + 2]);
+    u16::from_ne_bytes(bytes)
+}
+
+#[cfg(target_os = "linux")]
+fn read_u32(buf: &[u8], off: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&buf[off..off + 4]);
+    u32::from_ne_bytes(bytes)
+}
+
+/// Asks the kernel for the receive and send queue lengths of the Unix socket
+/// with the given inode via the `sock_diag` netlink interface.
+///
+/// For a listening socket these are the number of pending connections and
+/// the backlog limit respectively.
+#[cfg(target_os = "linux")]
+fn unix_diag_queues(ino: u64) -> io::Result<(usize, usize)> {
+    unsafe {
+        let sock = try!(cvt(libc::socket(libc::AF_NETLINK,
+                                         libc::SOCK_DGRAM,
+                                         libc::NETLINK_SOCK_DIAG)).map(Inner));
+
+        let mut req: UnixDiagRequest = mem::zeroed();
+        req.header.nlmsg_len = mem::size_of::<UnixDiagRequest>() as u32;
+        req.header.nlmsg_type = SOCK_DIAG_BY_FAMILY;
+        req.header.nlmsg_flags = libc::NLM_F_REQUEST as u16;
+        req.sdiag_family = libc::AF_UNIX as u8;
+        req.udiag_states = !0;
+        req.udiag_ino = ino as u32;
+        req.udiag_show = UDIAG_SHOW_RQLEN;
+        req.udiag_cookie = [!0, !0];
+        try!(sock.send(slice::from_raw_parts(&req as *const _ as *const u8,
+                                             mem::size_of::<UnixDiagRequest>())));
+
+        let mut buf = [0; 512];
+        let len = try!(sock.recv(&mut buf));
+        let header_len = mem::size_of::<libc::nlmsghdr>();
+        if len < header_len {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "short sock_diag response"));
+        }
+        let msg_len = cmp::min(read_u32(&buf, 0) as usize, len);
+        if read_u16(&buf, 4) == libc::NLMSG_ERROR as u16 {
+            let errno = read_u32(&buf, header_len) as i32;
+            return Err(io::Error::from_raw_os_error(-errno));
+        }
+
+        // attributes follow the fixed size unix_diag_msg
+        let mut off = header_len + 16;
+        while off + 4 <= msg_len {
+            let attr_len = read_u16(&buf, off) as usize;
+            if attr_len < 4 {
+                break;
+            }
+            if read_u16(&buf, off + 2) == UNIX_DIAG_RQLEN && off + 12 <= msg_len {
+                return Ok((read_u32(&buf, off + 4) as usize, read_u32(&buf, off + 8) as usize));
+            }
+            off += (attr_len + 3) & !3;
+        }
+
+        Err(io::Error::new(io::ErrorKind::InvalidData, "sock_diag response missing queue lengths"))
+    }
+}
+
+// Passed to receives so that Linux reports the real length of datagrams
+// that were truncated. Other platforms only report the truncation.
+#[cfg(target_os = "linux")]
+const RECV_TRUNC: libc::c_int = libc::MSG_TRUNC;
+#[cfg(not(target_os = "linux"))]
+const RECV_TRUNC: libc::c_int = 0;
+
+// Passed to receives of file descriptors so that they are created with
+// close-on-exec set. Elsewhere it is set on each descriptor after receipt.
+#[cfg(target_os = "linux")]
+const RECV_CMSG_CLOEXEC: libc::c_int = libc::MSG_CMSG_CLOEXEC;
+#[cfg(not(target_os = "linux"))]
+const RECV_CMSG_CLOEXEC: libc::c_int = 0;
+
+// The size of the control buffer allocated to receive ancillary data.
+const ANCILLARY_CAPACITY: usize = 2048;
+
+// An option value saved by `configure` so that a failed batch can be undone.
+enum SavedOption {
+    Timeout(libc::c_int, libc::timeval),
+    BufferSize(libc::c_int, libc::c_int),
+    StatusFlags(libc::c_int),
+    FdFlags(libc::c_int),
+}
+
+// The result of a `recvmsg` call.
+struct RecvMsg {
+    count: usize,
+    control_len: usize,
+    flags: libc::c_int,
+    addr: SocketAddr,
+}
+
+fn cvt_r<F>(mut f: F) -> io::Result<libc::ssize_t>
+    where F: FnMut() -> libc::ssize_t
+{
+    loop {
+        match cvt_s(f()) {
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            other => return other,
+        }
+    }
+}
+
+fn getsockopt<T: Copy>(fd: RawFd, level: libc::c_int, name: libc::c_int) -> io::Result<T> {
+    unsafe {
+        let mut value: T = mem::zeroed();
+        let mut len = mem::size_of::<T>() as libc::socklen_t;
+        try!(cvt(libc::getsockopt(fd,
+                                  level,
+                                  name,
+                                  &mut value as *mut _ as *mut _,
+                                  &mut len)));
+        Ok(value)
+    }
+}
+
+// Accepts a connection as a nonblocking, close-on-exec descriptor.
+#[cfg(target_os = "linux")]
+unsafe fn accept_nonblocking(fd: RawFd,
+                             addr: *mut libc::sockaddr,
+                             len: *mut libc::socklen_t)
+                             -> libc::c_int {
+    libc::accept4(fd, addr, len, libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC)
+}
+
+#[cfg(not(target_os = "linux"))]
+unsafe fn accept_nonblocking(fd: RawFd,
+                             addr: *mut libc::sockaddr,
+                             len: *mut libc::socklen_t)
+                             -> libc::c_int {
+    let fd = libc::accept(fd, addr, len);
+    if fd < 0 {
+        return fd;
+    }
+    let flags = libc::fcntl(fd, libc::F_GETFL);
+    if flags < 0 || libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) < 0 ||
+       libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) < 0 {
+        libc::close(fd);
+        return -1;
+    }
+    fd
+}
+
+// Accepts a connection as a close-on-exec descriptor, atomically where
+// supported.
+#[cfg(target_os = "linux")]
+unsafe fn accept_cloexec(fd: RawFd,
+                         addr: *mut libc::sockaddr,
+                         len: *mut libc::socklen_t)
+                         -> libc::c_int {
+    libc::accept4(fd, addr, len, libc::SOCK_CLOEXEC)
+}
+
+#[cfg(not(target_os = "linux"))]
+unsafe fn accept_cloexec(fd: RawFd,
+                         addr: *mut libc::sockaddr,
+                         len: *mut libc::socklen_t)
+                         -> libc::c_int {
+    let fd = libc::accept(fd, addr, len);
+    if fd < 0 {
+        return fd;
+    }
+    if libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) < 0 {
+        libc::close(fd);
+        return -1;
+    }
+    fd
+}
+
 struct Inner(RawFd);
 
 impl Drop for Inner {
@@ -55,22 +274,81 @@ impl Drop for Inner {
 }
 
 impl Inner {
+    // Creates a socket which is closed on exec, atomically where supported.
+    #[cfg(target_os = "linux")]
+    fn new(kind: libc::c_int) -> io::Result<Inner> {
+        unsafe { cvt(libc::socket(libc::AF_UNIX, kind | libc::SOCK_CLOEXEC, 0)).map(Inner) }
+    }
+
+    #[cfg(not(target_os = "linux"))]
     fn new(kind: libc::c_int) -> io::Result<Inner> {
+        let inner = try!(Inner::new_inheritable(kind));
+        try!(inner.set_cloexec(true));
+        Ok(inner)
+    }
+
+    // Creates a nonblocking socket which is closed on exec, atomically where
+    // supported.
+    #[cfg(target_os = "linux")]
+    fn new_nonblocking(kind: libc::c_int) -> io::Result<Inner> {
+        let kind = kind | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK;
+        unsafe { cvt(libc::socket(libc::AF_UNIX, kind, 0)).map(Inner) }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn new_nonblocking(kind: libc::c_int) -> io::Result<Inner> {
+        let inner = try!(Inner::new(kind));
+        try!(inner.set_nonblocking(true));
+        Ok(inner)
+    }
+
+    fn new_inheritable(kind: libc::c_int) -> io::Result<Inner> {
         unsafe { cvt(libc::socket(libc::AF_UNIX, kind, 0)).map(Inner) }
     }
 
+    // Creates a pair of sockets which are closed on exec, atomically where
+    // supported.
+    #[cfg(target_os = "linux")]
     fn new_pair(kind: libc::c_int) -> io::Result<(Inner, Inner)> {
         unsafe {
             let mut fds = [0, 0];
-            try!(cvt(libc::socketpair(libc::AF_UNIX, kind, 0, fds.as_mut_ptr())));
+            try!(cvt(libc::socketpair(libc::AF_UNIX, kind | libc::SOCK_CLOEXEC, 0, fds.as_mut_ptr())));
             Ok((Inner(fds[0]), Inner(fds[1])))
         }
     }
 
+    #[cfg(not(target_os = "linux"))]
+    fn new_pair(kind: libc::c_int) -> io::Result<(Inner, Inner)> {
+        let (i1, i2) = unsafe {
+            let mut fds = [0, 0];
+            try!(cvt(libc::socketpair(libc::AF_UNIX, kind, 0, fds.as_mut_ptr())));
+            (Inner(fds[0]), Inner(fds[1]))
+        };
+        try!(i1.set_cloexec(true));
+        try!(i2.set_cloexec(true));
+        Ok((i1, i2))
+    }
+
+    fn connect_address(&self, addr: Address) -> io::Result<()> {
+        if addr == Address::Unnamed {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "cannot connect to an unnamed address"));
+        }
+        let (addr, len) = try!(addr.sockaddr());
+        unsafe { cvt(libc::connect(self.0, &addr as *const _ as *const _, len)).map(|_| ()) }
+    }
+
     fn try_clone(&self) -> io::Result<Inner> {
         unsafe { cvt(libc::dup(self.0)).map(Inner) }
     }
 
+    fn connect<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        unsafe {
+            let (addr, len) = try!(sockaddr_un(path));
+            cvt(libc::connect(self.0, &addr as *const _ as *const _, len)).map(|_| ())
+        }
+    }
+
     fn shutdown(&self, how: Shutdown) -> io::Result<()> {
         let how = match how {
             Shutdown::Read => libc::SHUT_RD,
@@ -81,6 +359,16 @@ impl Inner {
         unsafe { cvt(libc::shutdown(self.0, how)).map(|_| ()) }
     }
 
+    fn set_timeouts(&self, read: Option<Duration>, write: Option<Duration>) -> io::Result<()> {
+        let previous = try!(self.getsockopt::<libc::timeval>(libc::SOL_SOCKET, libc::SO_RCVTIMEO));
+        try!(self.set_timeout(read, libc::SO_RCVTIMEO));
+        if let Err(e) = self.set_timeout(write, libc::SO_SNDTIMEO) {
+            let _ = self.setsockopt(libc::SOL_SOCKET, libc::SO_RCVTIMEO, previous);
+            return Err(e);
+        }
+        Ok(())
+    }
+
     fn timeout(&self, kind: libc::c_int) -> io::Result<Option<Duration>> {
         let timeout = unsafe {
             let mut timeout: libc::timeval = mem::zeroed();
@@ -96,6 +384,7 @@ impl Inner {
         if timeout.tv_sec == 0 && timeout.tv_usec == 0 {
             Ok(None)
         } else {
+            // tv_usec is always below one million, so this can't overflow
             Ok(Some(Duration::new(timeout.tv_sec as u64, (timeout.tv_usec as u32) * 1000)))
         }
     }
@@ -141,11 +430,74 @@ impl Inner {
         }
     }
 
+    fn setsockopt<T>(&self, level: libc::c_int, name: libc::c_int, value: T) -> io::Result<()> {
+        unsafe {
+            cvt(libc::setsockopt(self.0,
+                                 level,
+                                 name,
+                                 &value as *const _ as *const _,
+                                 mem::size_of::<T>() as libc::socklen_t))
+                .map(|_| ())
+        }
+    }
+
+    fn getsockopt<T: Copy>(&self, level: libc::c_int, name: libc::c_int) -> io::Result<T> {
+        getsockopt(self.0, level, name)
+    }
+
+    fn set_buffer_size(&self, size: usize, kind: libc::c_int) -> io::Result<()> {
+        if size > libc::c_int::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "buffer size too large"));
+        }
+        self.setsockopt(libc::SOL_SOCKET, kind, size as libc::c_int)
+    }
+
+    fn buffer_size(&self, kind: libc::c_int) -> io::Result<usize> {
+        self.getsockopt::<libc::c_int>(libc::SOL_SOCKET, kind).map(|size| size as usize)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn recv_buffer_limits(&self) -> io::Result<(usize, usize)> {
+        let current = try!(self.buffer_size(libc::SO_RCVBUF));
+        let max = try!(fs::read_to_string("/proc/sys/net/core/rmem_max"));
+        match max.trim().parse() {
+            Ok(max) => Ok((current, max)),
+            Err(_) => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid rmem_max value")),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn recv_buffer_limits(&self) -> io::Result<(usize, usize)> {
+        Err(io::Error::new(io::ErrorKind::Other,
+                           "the receive buffer limit is not available on this platform"))
+    }
+
+    fn nonblocking(&self) -> io::Result<bool> {
+        let flags = unsafe { try!(cvt(libc::fcntl(self.0, libc::F_GETFL))) };
+        Ok(flags & libc::O_NONBLOCK != 0)
+    }
+
     fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
         let mut nonblocking = nonblocking as libc::c_ulong;
         unsafe { cvt(libc::ioctl(self.0, libc::FIONBIO, &mut nonblocking)).map(|_| ()) }
     }
 
+    fn swap_nonblocking(&self, nonblocking: bool) -> io::Result<bool> {
+        let flags = unsafe { try!(cvt(libc::fcntl(self.0, libc::F_GETFL))) };
+        let old = flags & libc::O_NONBLOCK != 0;
+        if old != nonblocking {
+            let flags = if nonblocking {
+                flags | libc::O_NONBLOCK
+            } else {
+                flags & !libc::O_NONBLOCK
+            };
+            unsafe {
+                try!(cvt(libc::fcntl(self.0, libc::F_SETFL, flags)));
+            }
+        }
+        Ok(old)
+    }
+
     fn take_error(&self) -> io::Result<Option<io::Error>> {
         let mut errno: libc::c_int = 0;
 
@@ -164,1476 +516,7717 @@ impl Inner {
         }
     }
 
-    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+    #[cfg(target_os = "linux")]
+    fn send_queue_len(&self) -> io::Result<usize> {
+        let mut len: libc::c_int = 0;
+        // SIOCOUTQ shares its value with TIOCOUTQ
         unsafe {
-            let count = try!(cvt_s(libc::recv(self.0,
-                                              buf.as_mut_ptr() as *mut _,
-                                              buf.len(),
-                                              0)));
-            Ok(count as usize)
+            try!(cvt(libc::ioctl(self.0, libc::TIOCOUTQ, &mut len)));
         }
+        Ok(len as usize)
     }
 
-    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
-        unsafe {
-            let count = try!(cvt_s(libc::send(self.0,
-                                              buf.as_ptr() as *const _,
-                                              buf.len(),
-                                              0)));
-            Ok(count as usize)
+    #[cfg(not(target_os = "linux"))]
+    fn send_queue_len(&self) -> io::Result<usize> {
+        Ok(0)
+    }
+
+    fn wait_send_queue(&self, timeout: Option<Duration>) -> io::Result<usize> {
+        let start = Instant::now();
+        loop {
+            let len = try!(self.send_queue_len());
+            let expired = match timeout {
+                Some(timeout) => start.elapsed() >= timeout,
+                None => false,
+            };
+            if len == 0 || expired {
+                return Ok(len);
+            }
+            thread::sleep(Duration::from_millis(1));
         }
     }
-}
 
-unsafe fn sockaddr_un<P: AsRef<Path>>(path: P) -> io::Result<(libc::sockaddr_un, libc::socklen_t)> {
-    let mut addr: libc::sockaddr_un = mem::zeroed();
-    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    #[cfg(target_os = "linux")]
+    fn same_socket(&self, other: &Inner) -> io::Result<bool> {
+        let res = unsafe {
+            let pid = libc::getpid();
+            cvt(libc::syscall(libc::SYS_kcmp, pid, pid, KCMP_FILE, self.0, other.0) as libc::c_int)
+        };
+        match res {
+            Ok(res) => Ok(res == 0),
+            // kcmp is only present when the kernel supports checkpointing
+            Err(ref e) if e.raw_os_error() == Some(libc::ENOSYS) ||
+                          e.raw_os_error() == Some(libc::EPERM) => self.same_inode(other),
+            Err(e) => Err(e),
+        }
+    }
 
-    let bytes = path.as_ref().as_os_str().as_bytes();
+    #[cfg(not(target_os = "linux"))]
+    fn same_socket(&self, other: &Inner) -> io::Result<bool> {
+        self.same_inode(other)
+    }
 
-    match (bytes.get(0), bytes.len().cmp(&addr.sun_path.len())) {
-        // Abstract paths don't need a null terminator
-        (Some(&0), Ordering::Greater) => {
-            return Err(io::Error::new(io::ErrorKind::InvalidInput,
-                                      "path must be no longer than SUN_LEN"));
-        }
-        (_, Ordering::Greater) | (_, Ordering::Equal) => {
-            return Err(io::Error::new(io::ErrorKind::InvalidInput,
-                                      "path must be shorter than SUN_LEN"));
+    fn same_inode(&self, other: &Inner) -> io::Result<bool> {
+        unsafe {
+            let mut stat: libc::stat = mem::zeroed();
+            let mut other_stat: libc::stat = mem::zeroed();
+            try!(cvt(libc::fstat(self.0, &mut stat)));
+            try!(cvt(libc::fstat(other.0, &mut other_stat)));
+            Ok(stat.st_dev == other_stat.st_dev && stat.st_ino == other_stat.st_ino)
         }
-        _ => {}
     }
-    for (dst, src) in addr.sun_path.iter_mut().zip(bytes.iter()) {
-        *dst = *src as libc::c_char;
+
+    #[cfg(target_os = "linux")]
+    fn listen_queues(&self) -> io::Result<(usize, usize)> {
+        let ino = unsafe {
+            let mut stat: libc::stat = mem::zeroed();
+            try!(cvt(libc::fstat(self.0, &mut stat)));
+            stat.st_ino
+        };
+        unix_diag_queues(ino as u64)
     }
-    // null byte for pathname addresses is already there because we zeroed the
-    // struct
 
-    let mut len = sun_path_offset() + bytes.len();
-    match bytes.get(0) {
-        Some(&0) | None => {}
-        Some(_) => len += 1,
+    #[cfg(not(target_os = "linux"))]
+    fn listen_queues(&self) -> io::Result<(usize, usize)> {
+        Err(io::Error::new(io::ErrorKind::Other,
+                           "listen queue lengths are not available on this platform"))
     }
-    Ok((addr, len as libc::socklen_t))
-}
 
-enum AddressKind<'a> {
-    Unnamed,
-    Pathname(&'a Path),
-    Abstract(&'a [u8]),
-}
+    #[cfg(target_os = "linux")]
+    fn accepting(&self) -> io::Result<bool> {
+        is_listening(self.0)
+    }
 
-/// An address associated with a Unix socket.
-#[derive(Clone)]
-pub struct SocketAddr {
-    addr: libc::sockaddr_un,
-    len: libc::socklen_t,
-}
+    #[cfg(not(target_os = "linux"))]
+    fn accepting(&self) -> io::Result<bool> {
+        Err(io::Error::new(io::ErrorKind::Other,
+                           "SO_ACCEPTCONN is not supported on this platform"))
+    }
 
-impl SocketAddr {
-    fn new<F>(f: F) -> io::Result<SocketAddr>
-        where F: FnOnce(*mut libc::sockaddr, *mut libc::socklen_t) -> libc::c_int
-    {
-        unsafe {
-            let mut addr: libc::sockaddr_un = mem::zeroed();
-            let mut len = mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
-            try!(cvt(f(&mut addr as *mut _ as *mut _, &mut len)));
+    fn close(self) -> io::Result<()> {
+        let fd = self.0;
+        mem::forget(self);
+        unsafe { cvt(libc::close(fd)).map(|_| ()) }
+    }
 
-            if len == 0 {
-                // When there is a datagram from unnamed unix socket
-                // linux returns zero bytes of address
-                len = sun_path_offset() as libc::socklen_t;  // i.e. zero-length address
-            } else if addr.sun_family != libc::AF_UNIX as libc::sa_family_t {
-                return Err(io::Error::new(io::ErrorKind::InvalidInput,
-                                          "file descriptor did not correspond to a Unix socket"));
-            }
+    fn set_cloexec(&self, cloexec: bool) -> io::Result<()> {
+        let flags = try!(self.fd_flags());
+        let flags = if cloexec {
+            flags | libc::FD_CLOEXEC
+        } else {
+            flags & !libc::FD_CLOEXEC
+        };
+        unsafe { cvt(libc::fcntl(self.0, libc::F_SETFD, flags)).map(|_| ()) }
+    }
 
-            Ok(SocketAddr {
-                addr: addr,
-                len: len,
+    fn configure(&self, cfg: &SocketConfig) -> Result<(), ConfigError> {
+        let mut saved = vec![];
+        let res = self.apply_config(cfg, &mut saved);
+        if res.is_err() {
+            for option in saved.iter().rev() {
+                let _ = self.restore_option(option);
+            }
+        }
+        res
+    }
+
+    fn apply_config(&self,
+                    cfg: &SocketConfig,
+                    saved: &mut Vec<SavedOption>)
+                    -> Result<(), ConfigError> {
+        fn check<T>(option: &'static str, res: io::Result<T>) -> Result<T, ConfigError> {
+            res.map_err(|e| {
+                ConfigError {
+                    option: option,
+                    source: e,
+                }
             })
         }
-    }
 
-    /// Returns true iff the address is unnamed.
-    pub fn is_unnamed(&self) -> bool {
-        if let AddressKind::Unnamed = self.address() {
-            true
-        } else {
-            false
+        if let Some(timeout) = cfg.read_timeout {
+            let old = try!(check("read_timeout",
+                                 self.getsockopt(libc::SOL_SOCKET, libc::SO_RCVTIMEO)));
+            saved.push(SavedOption::Timeout(libc::SO_RCVTIMEO, old));
+            try!(check("read_timeout", self.set_timeout(Some(timeout), libc::SO_RCVTIMEO)));
+        }
+        if let Some(timeout) = cfg.write_timeout {
+            let old = try!(check("write_timeout",
+                                 self.getsockopt(libc::SOL_SOCKET, libc::SO_SNDTIMEO)));
+            saved.push(SavedOption::Timeout(libc::SO_SNDTIMEO, old));
+            try!(check("write_timeout", self.set_timeout(Some(timeout), libc::SO_SNDTIMEO)));
+        }
+        if let Some(size) = cfg.send_buffer_size {
+            let old = try!(check("send_buffer_size",
+                                 self.getsockopt(libc::SOL_SOCKET, libc::SO_SNDBUF)));
+            saved.push(SavedOption::BufferSize(libc::SO_SNDBUF, old));
+            try!(check("send_buffer_size", self.set_buffer_size(size, libc::SO_SNDBUF)));
+        }
+        if let Some(size) = cfg.recv_buffer_size {
+            let old = try!(check("recv_buffer_size",
+                                 self.getsockopt(libc::SOL_SOCKET, libc::SO_RCVBUF)));
+            saved.push(SavedOption::BufferSize(libc::SO_RCVBUF, old));
+            try!(check("recv_buffer_size", self.set_buffer_size(size, libc::SO_RCVBUF)));
         }
+        if let Some(nonblocking) = cfg.nonblocking {
+            let old = try!(check("nonblocking", unsafe { cvt(libc::fcntl(self.0, libc::F_GETFL)) }));
+            saved.push(SavedOption::StatusFlags(old));
+            try!(check("nonblocking", self.set_nonblocking(nonblocking)));
+        }
+        if let Some(cloexec) = cfg.cloexec {
+            saved.push(SavedOption::FdFlags(try!(check("cloexec", self.fd_flags()))));
+            try!(check("cloexec", self.set_cloexec(cloexec)));
+        }
+        Ok(())
     }
 
-    /// Returns the contents of this address if it is a `pathname` address.
-    pub fn as_pathname(&self) -> Option<&Path> {
-        if let AddressKind::Pathname(path) = self.address() {
-            Some(path)
-        } else {
-            None
+    fn restore_option(&self, option: &SavedOption) -> io::Result<()> {
+        match *option {
+            SavedOption::Timeout(kind, timeout) => {
+                self.setsockopt(libc::SOL_SOCKET, kind, timeout)
+            }
+            SavedOption::BufferSize(kind, size) => {
+                // Linux doubles the size it is given, and reports the doubled
+                // value
+                let size = if cfg!(target_os = "linux") { size / 2 } else { size };
+                self.setsockopt(libc::SOL_SOCKET, kind, size)
+            }
+            SavedOption::StatusFlags(flags) => unsafe {
+                cvt(libc::fcntl(self.0, libc::F_SETFL, flags)).map(|_| ())
+            },
+            SavedOption::FdFlags(flags) => unsafe {
+                cvt(libc::fcntl(self.0, libc::F_SETFD, flags)).map(|_| ())
+            },
         }
     }
 
-    fn address<'a>(&'a self) -> AddressKind<'a> {
-        let len = self.len as usize - sun_path_offset();
-        let path = unsafe { mem::transmute::<&[libc::c_char], &[u8]>(&self.addr.sun_path) };
+    fn fd_flags(&self) -> io::Result<libc::c_int> {
+        unsafe { cvt(libc::fcntl(self.0, libc::F_GETFD)) }
+    }
 
-        // OSX seems to return a len of 16 and a zeroed sun_path for unnamed addresses
-        if len == 0 || (cfg!(not(target_os = "linux")) && self.addr.sun_path[0] == 0) {
-            AddressKind::Unnamed
-        } else if self.addr.sun_path[0] == 0 {
-            AddressKind::Abstract(&path[1..len])
-        } else {
-            AddressKind::Pathname(OsStr::from_bytes(&path[..len - 1]).as_ref())
+    fn set_read_timeout_or_nonblocking(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match timeout {
+            Some(timeout) if timeout == Duration::from_secs(0) => {
+                try!(self.set_timeout(None, libc::SO_RCVTIMEO));
+                self.set_nonblocking(true)
+            }
+            timeout => {
+                try!(self.set_timeout(timeout, libc::SO_RCVTIMEO));
+                self.set_nonblocking(false)
+            }
         }
     }
-}
 
-impl fmt::Debug for SocketAddr {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        match self.address() {
-            AddressKind::Unnamed => write!(fmt, "(unnamed)"),
-            AddressKind::Abstract(name) => write!(fmt, "{} (abstract)", AsciiEscaped(name)),
-            AddressKind::Pathname(path) => write!(fmt, "{:?} (pathname)", path),
+    #[cfg(target_os = "linux")]
+    fn set_priority(&self, priority: u32) -> io::Result<()> {
+        if priority > libc::c_int::MAX as u32 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "priority too large"));
         }
+        self.setsockopt(libc::SOL_SOCKET, libc::SO_PRIORITY, priority as libc::c_int)
     }
-}
 
-struct AsciiEscaped<'a>(&'a [u8]);
+    #[cfg(target_os = "linux")]
+    fn priority(&self) -> io::Result<u32> {
+        self.getsockopt::<libc::c_int>(libc::SOL_SOCKET, libc::SO_PRIORITY).map(|v| v as u32)
+    }
 
-impl<'a> fmt::Display for AsciiEscaped<'a> {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        try!(write!(fmt, "\""));
-        for byte in self.0.iter().cloned().flat_map(ascii::escape_default) {
-            try!(write!(fmt, "{}", byte as char));
+    #[cfg(target_os = "linux")]
+    fn peer_groups(&self) -> io::Result<Vec<u32>> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid process status");
+        let cred = try!(self.getsockopt::<libc::ucred>(libc::SOL_SOCKET, libc::SO_PEERCRED));
+        if cred.pid == 0 {
+            return Err(io::Error::new(io::ErrorKind::NotFound,
+                                      "peer process is not visible"));
         }
-        write!(fmt, "\"")
+        let status = try!(fs::read_to_string(format!("/proc/{}/status", cred.pid)));
+
+        let mut uid = None;
+        let mut groups = vec![cred.gid];
+        for line in status.lines() {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("Uid:") => {
+                    // The real, effective, saved and filesystem user IDs
+                    match fields.nth(1).map(str::parse) {
+                        Some(Ok(euid)) => uid = Some(euid),
+                        _ => return Err(invalid()),
+                    }
+                }
+                Some("Groups:") => {
+                    for group in fields {
+                        let group = try!(group.parse().map_err(|_| invalid()));
+                        if !groups.contains(&group) {
+                            groups.push(group);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // The pid may have been reused by an unrelated process
+        if uid != Some(cred.uid) {
+            return Err(io::Error::new(io::ErrorKind::NotFound,
+                                      "peer process no longer exists"));
+        }
+        Ok(groups)
     }
-}
 
-/// OS specific extension traits.
-pub mod os {
-    /// Linux specific extension traits.
     #[cfg(target_os = "linux")]
-    pub mod linux {
-        use {AddressKind, SocketAddr};
+    fn recv_errqueue(&self, buf: &mut [u8], control: &mut [u8]) -> io::Result<(usize, usize)> {
+        let flags = libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT;
+        let msg = try!(self.recv_msg(&mut [IoSliceMut::new(buf)], control, flags));
+        Ok((msg.count, msg.control_len))
+    }
+
+    fn socket_type(&self) -> io::Result<SocketType> {
+        match try!(self.getsockopt::<libc::c_int>(libc::SOL_SOCKET, libc::SO_TYPE)) {
+            libc::SOCK_STREAM => Ok(SocketType::Stream),
+            libc::SOCK_DGRAM => Ok(SocketType::Datagram),
+            libc::SOCK_SEQPACKET => Ok(SocketType::Seqpacket),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "unknown socket type")),
+        }
+    }
 
-        /// Linux specific extensions for the `SocketAddr` type.
-        pub trait SocketAddrExt {
-            /// Returns the contents of this address (without the leading
-            /// null byte) if it is an `abstract` address.
-            fn as_abstract(&self) -> Option<&[u8]>;
+    fn poll(&self, events: libc::c_short, timeout: Option<Duration>) -> io::Result<Ready> {
+        let timeout = match timeout {
+            Some(timeout) => {
+                // Round up so that a short timeout doesn't become a poll
+                let mut millis = timeout.as_secs()
+                                        .saturating_mul(1000)
+                                        .saturating_add(timeout.subsec_nanos() as u64 / 1_000_000);
+                if timeout.subsec_nanos() % 1_000_000 != 0 {
+                    millis = millis.saturating_add(1);
+                }
+                cmp::min(millis, libc::c_int::MAX as u64) as libc::c_int
+            }
+            None => -1,
+        };
+        let mut pollfd = libc::pollfd {
+            fd: self.0,
+            events: events,
+            revents: 0,
+        };
+        loop {
+            match unsafe { cvt(libc::poll(&mut pollfd, 1, timeout)) } {
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+                Ok(_) => return Ok(Ready(pollfd.revents)),
+            }
         }
+    }
 
-        impl SocketAddrExt for SocketAddr {
-            fn as_abstract(&self) -> Option<&[u8]> {
-                if let AddressKind::Abstract(path) = self.address() {
-                    Some(path)
+    fn recv_msg(&self,
+                bufs: &mut [IoSliceMut],
+                control: &mut [u8],
+                flags: libc::c_int)
+                -> io::Result<RecvMsg> {
+        // Descriptors passed by the peer must not leak into child processes
+        let flags = if control.is_empty() {
+            flags
+        } else {
+            flags | RECV_CMSG_CLOEXEC
+        };
+        let mut count = 0;
+        let mut control_len = 0;
+        let mut msg_flags = 0;
+        let addr = try!(SocketAddr::new(|addr, len| {
+            unsafe {
+                let mut msg: libc::msghdr = mem::zeroed();
+                msg.msg_name = addr as *mut _;
+                msg.msg_namelen = *len;
+                // IoSliceMut is guaranteed to be ABI compatible with iovec
+                msg.msg_iov = bufs.as_mut_ptr() as *mut libc::iovec;
+                msg.msg_iovlen = bufs.len() as _;
+                if !control.is_empty() {
+                    msg.msg_control = control.as_mut_ptr() as *mut _;
+                    msg.msg_controllen = control.len() as _;
+                }
+
+                count = libc::recvmsg(self.0, &mut msg, flags);
+                *len = msg.msg_namelen;
+                control_len = msg.msg_controllen as usize;
+                msg_flags = msg.msg_flags;
+                if count > 0 {
+                    1
+                } else if count == 0 {
+                    0
                 } else {
-                    None
+                    -1
+                }
+            }
+        }).map_err(|e| self.recv_error(e, flags)));
+
+        if RECV_CMSG_CLOEXEC == 0 && control_len > 0 {
+            for fd in Ancillary::parse(&control[..control_len], msg_flags).fds() {
+                unsafe {
+                    libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
                 }
             }
         }
+
+        Ok(RecvMsg {
+            count: count as usize,
+            control_len: control_len,
+            flags: msg_flags,
+            addr: addr,
+        })
     }
-}
 
-/// A Unix stream socket.
-///
-/// # Examples
-///
-/// ```rust,no_run
-/// use unix_socket::UnixStream;
-/// use std::io::prelude::*;
-///
-/// let mut stream = UnixStream::connect("/path/to/my/socket").unwrap();
-/// stream.write_all(b"hello world").unwrap();
-/// let mut response = String::new();
-/// stream.read_to_string(&mut response).unwrap();
-/// println!("{}", response);
-/// ```
-pub struct UnixStream {
-    inner: Inner,
-}
+    fn recv_from(&self, buf: &mut [u8], flags: libc::c_int) -> io::Result<(usize, SocketAddr)> {
+        // recvfrom only ever writes initialized bytes into the buffer
+        let buf = unsafe {
+            slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut MaybeUninit<u8>, buf.len())
+        };
+        self.recv_from_uninit(buf, flags)
+    }
 
-impl fmt::Debug for UnixStream {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let mut builder = fmt.debug_struct("UnixStream");
-        builder.field("fd", &self.inner.0);
-        if let Ok(addr) = self.local_addr() {
-            builder.field("local", &addr);
-        }
-        if let Ok(addr) = self.peer_addr() {
-            builder.field("peer", &addr);
-        }
-        builder.finish()
+    fn recv_from_uninit(&self,
+                        buf: &mut [MaybeUninit<u8>],
+                        flags: libc::c_int)
+                        -> io::Result<(usize, SocketAddr)> {
+        let mut count = 0;
+        let addr = try!(SocketAddr::new(|addr, len| {
+            unsafe {
+                count = libc::recvfrom(self.0,
+                                       buf.as_mut_ptr() as *mut _,
+                                       buf.len(),
+                                       flags,
+                                       addr,
+                                       len);
+                if count > 0 {
+                    1
+                } else if count == 0 {
+                    0
+                } else {
+                    -1
+                }
+            }
+        }).map_err(|e| self.recv_error(e, flags)));
+
+        Ok((count as usize, addr))
     }
-}
 
-impl UnixStream {
-    /// Connects to the socket named by `path`.
-    ///
-    /// Linux provides, as a nonportable extension, a separate "abstract"
-    /// address namespace as opposed to filesystem-based addressing. If `path`
-    /// begins with a null byte, it will be interpreted as an "abstract"
-    /// address. Otherwise, it will be interpreted as a "pathname" address,
-    /// corresponding to a path on the filesystem.
-    pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<UnixStream> {
+    #[cfg(target_os = "linux")]
+    fn peek_datagram_len(&self, flags: libc::c_int) -> io::Result<usize> {
+        // With MSG_TRUNC, Linux reports the real length of the datagram even
+        // though nothing is copied out
         unsafe {
-            let inner = try!(Inner::new(libc::SOCK_STREAM));
-            let (addr, len) = try!(sockaddr_un(path));
+            let count = try!(cvt_s(libc::recv(self.0,
+                                              ptr::null_mut(),
+                                              0,
+                                              flags | libc::MSG_PEEK | libc::MSG_TRUNC))
+                                 .map_err(|e| self.recv_error(e, flags)));
+            Ok(count as usize)
+        }
+    }
 
-            let ret = libc::connect(inner.0, &addr as *const _ as *const _, len);
-            if ret < 0 {
-                Err(io::Error::last_os_error())
-            } else {
-                Ok(UnixStream { inner: inner })
+    #[cfg(not(target_os = "linux"))]
+    fn peek_datagram_len(&self, flags: libc::c_int) -> io::Result<usize> {
+        // Elsewhere we have to peek with progressively larger buffers until
+        // the datagram is no longer truncated
+        let mut buf = vec![0u8; 1024];
+        loop {
+            unsafe {
+                let mut iov = libc::iovec {
+                    iov_base: buf.as_mut_ptr() as *mut _,
+                    iov_len: buf.len(),
+                };
+                let mut msg: libc::msghdr = mem::zeroed();
+                msg.msg_iov = &mut iov;
+                msg.msg_iovlen = 1;
+                let count = try!(cvt_s(libc::recvmsg(self.0, &mut msg, flags | libc::MSG_PEEK))
+                                     .map_err(|e| self.recv_error(e, flags)));
+                if msg.msg_flags & libc::MSG_TRUNC == 0 {
+                    return Ok(count as usize);
+                }
             }
+            let len = buf.len() * 2;
+            buf.resize(len, 0);
         }
     }
 
-    /// Creates an unnamed pair of connected sockets.
-    ///
-    /// Returns two `UnixStream`s which are connected to each other.
-    pub fn pair() -> io::Result<(UnixStream, UnixStream)> {
-        let (i1, i2) = try!(Inner::new_pair(libc::SOCK_STREAM));
-        Ok((UnixStream { inner: i1 }, UnixStream { inner: i2 }))
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv_with_flags(buf, 0)
     }
 
-    /// Creates a new independently owned handle to the underlying socket.
-    ///
-    /// The returned `UnixStream` is a reference to the same stream that this
-    /// object references. Both handles will read and write the same stream of
-    /// data, and options set on one stream will be propogated to the other
-    /// stream.
-    pub fn try_clone(&self) -> io::Result<UnixStream> {
-        Ok(UnixStream { inner: try!(self.inner.try_clone()) })
+    fn recv_with_flags(&self, buf: &mut [u8], flags: libc::c_int) -> io::Result<usize> {
+        // recv only writes to the buffer, so it may be treated as uninitialized
+        let buf = unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut MaybeUninit<u8>, buf.len()) };
+        self.recv_uninit(buf, flags)
     }
 
-    /// Returns the socket address of the local half of this connection.
-    pub fn local_addr(&self) -> io::Result<SocketAddr> {
-        SocketAddr::new(|addr, len| unsafe { libc::getsockname(self.inner.0, addr, len) })
+    fn recv_uninit(&self, buf: &mut [MaybeUninit<u8>], flags: libc::c_int) -> io::Result<usize> {
+        unsafe {
+            let count = try!(cvt_s(libc::recv(self.0,
+                                              buf.as_mut_ptr() as *mut _,
+                                              buf.len(),
+                                              flags))
+                                 .map_err(|e| self.recv_error(e, flags)));
+            Ok(count as usize)
+        }
     }
 
-    /// Returns the socket address of the remote half of this connection.
-    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
-        SocketAddr::new(|addr, len| unsafe { libc::getpeername(self.inner.0, addr, len) })
+    fn recv_deadline(&self, buf: &mut [u8], deadline: Instant) -> io::Result<usize> {
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::from_raw_os_error(libc::ETIMEDOUT));
+            }
+            if try!(self.poll(libc::POLLIN, Some(deadline - now))).0 == 0 {
+                continue;
+            }
+            match self.recv_with_flags(buf, libc::MSG_DONTWAIT) {
+                // Another reader may have consumed the data first
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                res => return res,
+            }
+        }
     }
 
-    /// Sets the read timeout for the socket.
-    ///
-    /// If the provided value is `None`, then `read` calls will block
-    /// indefinitely. It is an error to pass the zero `Duration` to this
-    /// method.
-    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
-        self.inner.set_timeout(timeout, libc::SO_RCVTIMEO)
+    // Both a zero-length record and end of file receive zero bytes, but with
+    // SO_PASSCRED enabled credentials accompany every record, so only end of
+    // file comes without control data.
+    #[cfg(target_os = "linux")]
+    fn recv_record(&self, buf: &mut [u8]) -> io::Result<Option<usize>> {
+        // The option is shared by every handle to the socket, so toggling it
+        // here would race with other readers
+        if try!(self.getsockopt::<libc::c_int>(libc::SOL_SOCKET, libc::SO_PASSCRED)) == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "SO_PASSCRED must be enabled"));
+        }
+        // Only credentials fit, so any passed file descriptors are discarded
+        // just as they are by recv
+        let mut control = vec![0; unsafe {
+            libc::CMSG_SPACE(mem::size_of::<libc::ucred>() as _) as usize
+        }];
+        let msg = try!(self.recv_msg(&mut [IoSliceMut::new(buf)], &mut control, 0));
+
+        if msg.count == 0 && msg.control_len == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(msg.count))
+        }
     }
 
-    /// Sets the write timeout for the socket.
-    ///
-    /// If the provided value is `None`, then `write` calls will block
-    /// indefinitely. It is an error to pass the zero `Duration` to this
-    /// method.
-    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
-        self.inner.set_timeout(timeout, libc::SO_SNDTIMEO)
+    // Elsewhere every record is terminated by MSG_EOR, which end of file
+    // lacks.
+    #[cfg(not(target_os = "linux"))]
+    fn recv_record(&self, buf: &mut [u8]) -> io::Result<Option<usize>> {
+        let msg = try!(self.recv_msg(&mut [IoSliceMut::new(buf)], &mut [], 0));
+        if msg.count == 0 && msg.flags & libc::MSG_EOR == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(msg.count))
+        }
     }
 
-    /// Returns the read timeout of this socket.
-    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
-        self.inner.timeout(libc::SO_RCVTIMEO)
+    // A blocking receive that runs into SO_RCVTIMEO fails with EAGAIN, which
+    // is indistinguishable from a nonblocking receive finding nothing to
+    // read, so report it as a timeout instead.
+    fn recv_error(&self, err: io::Error, flags: libc::c_int) -> io::Error {
+        if err.kind() != io::ErrorKind::WouldBlock || flags & libc::MSG_DONTWAIT != 0 {
+            return err;
+        }
+        match (self.nonblocking(), self.timeout(libc::SO_RCVTIMEO)) {
+            (Ok(false), Ok(Some(_))) => io::Error::from_raw_os_error(libc::ETIMEDOUT),
+            _ => err,
+        }
     }
 
-    /// Returns the write timeout of this socket.
-    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
-        self.inner.timeout(libc::SO_SNDTIMEO)
+    fn send_to(&self,
+               buf: &[u8],
+               addr: &libc::sockaddr_un,
+               len: libc::socklen_t,
+               flags: libc::c_int)
+               -> io::Result<usize> {
+        let count = try!(cvt_r(|| unsafe {
+            libc::sendto(self.0,
+                         buf.as_ptr() as *const _,
+                         buf.len(),
+                         flags,
+                         addr as *const _ as *const _,
+                         len)
+        }));
+        Ok(count as usize)
     }
 
-    /// Moves the socket into or out of nonblocking mode.
-    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
-        self.inner.set_nonblocking(nonblocking)
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.send_with_flags(buf, 0)
     }
 
-    /// Returns the value of the `SO_ERROR` option.
-    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
-        self.inner.take_error()
+    fn send_with_flags(&self, buf: &[u8], flags: libc::c_int) -> io::Result<usize> {
+        let count = try!(cvt_r(|| unsafe {
+            libc::send(self.0, buf.as_ptr() as *const _, buf.len(), flags)
+        }));
+        Ok(count as usize)
     }
 
-    /// Shuts down the read, write, or both halves of this connection.
-    ///
-    /// This function will cause all pending and future I/O calls on the
-    /// specified portions to immediately return with an appropriate value
-    /// (see the documentation of `Shutdown`).
-    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
-        self.inner.shutdown(how)
+    fn try_send_poll(&self, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.send_with_flags(buf, libc::MSG_DONTWAIT) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            res => Poll::Ready(res),
+        }
     }
-}
 
-impl io::Read for UnixStream {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        io::Read::read(&mut &*self, buf)
+    fn send_iovecs(&self, iovecs: &mut [libc::iovec]) -> io::Result<usize> {
+        let count = try!(cvt_r(|| unsafe {
+            let mut msg: libc::msghdr = mem::zeroed();
+            msg.msg_iov = iovecs.as_mut_ptr();
+            msg.msg_iovlen = iovecs.len() as _;
+            libc::sendmsg(self.0, &msg, 0)
+        }));
+        Ok(count as usize)
+    }
+
+    fn send_fds(&self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        let fds_len = mem::size_of_val(fds);
+        let mut control = vec![0u8; unsafe { libc::CMSG_SPACE(fds_len as _) as usize }];
+        let count = try!(cvt_r(|| unsafe {
+            let mut iov = libc::iovec {
+                iov_base: buf.as_ptr() as *mut _,
+                iov_len: buf.len(),
+            };
+            let mut msg: libc::msghdr = mem::zeroed();
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            if !fds.is_empty() {
+                msg.msg_control = control.as_mut_ptr() as *mut _;
+                msg.msg_controllen = control.len() as _;
+
+                let cmsg = libc::CMSG_FIRSTHDR(&msg);
+                (*cmsg).cmsg_level = libc::SOL_SOCKET;
+                (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+                (*cmsg).cmsg_len = libc::CMSG_LEN(fds_len as _) as _;
+                ptr::copy_nonoverlapping(fds.as_ptr(),
+                                         libc::CMSG_DATA(cmsg) as *mut RawFd,
+                                         fds.len());
+            }
+            libc::sendmsg(self.0, &msg, 0)
+        }));
+        Ok(count as usize)
     }
-}
 
-impl<'a> io::Read for &'a UnixStream {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner.recv(buf)
+    fn recv_fds(&self, bufs: &mut [IoSliceMut]) -> io::Result<(RecvMsg, Vec<RawFd>)> {
+        let mut control = vec![0; ANCILLARY_CAPACITY];
+        let msg = try!(self.recv_msg(bufs, &mut control, 0));
+        let fds = Ancillary::parse(&control[..msg.control_len], msg.flags).fds();
+        Ok((msg, fds))
     }
 }
 
-impl io::Write for UnixStream {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        io::Write::write(&mut &*self, buf)
-    }
+// Removes a socket's path from the filesystem when dropped.
+struct UnlinkGuard(Option<PathBuf>);
 
-    fn flush(&mut self) -> io::Result<()> {
-        io::Write::flush(&mut &*self)
+impl Drop for UnlinkGuard {
+    fn drop(&mut self) {
+        if let Some(ref path) = self.0 {
+            let _ = fs::remove_file(path);
+        }
     }
 }
 
-impl<'a> io::Write for &'a UnixStream {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.inner.send(buf)
-    }
+impl UnlinkGuard {
+    fn set(&mut self, unlink: bool, addr: io::Result<SocketAddr>) -> io::Result<()> {
+        if !unlink {
+            self.0 = None;
+            return Ok(());
+        }
 
-    fn flush(&mut self) -> io::Result<()> {
-        Ok(())
+        match try!(addr).as_pathname() {
+            Some(path) => {
+                self.0 = Some(path.to_path_buf());
+                Ok(())
+            }
+            None => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                   "socket is not bound to a pathname address"))
+            }
+        }
     }
 }
 
-impl AsRawFd for UnixStream {
-    fn as_raw_fd(&self) -> RawFd {
-        self.inner.0
-    }
+/// Returns the maximum length in bytes of a pathname socket address on this
+/// platform.
+///
+/// This is the size of `sun_path` less one byte for the null terminator.
+/// Binding or connecting to a longer path fails with `InvalidInput`.
+pub fn max_path_len() -> usize {
+    let addr: libc::sockaddr_un = unsafe { mem::zeroed() };
+    addr.sun_path.len() - 1
 }
 
-impl FromRawFd for UnixStream {
-    unsafe fn from_raw_fd(fd: RawFd) -> UnixStream {
-        UnixStream { inner: Inner(fd) }
+unsafe fn sockaddr_un<P: AsRef<Path>>(path: P) -> io::Result<(libc::sockaddr_un, libc::socklen_t)> {
+    let mut addr: libc::sockaddr_un = mem::zeroed();
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+
+    let bytes = path.as_ref().as_os_str().as_bytes();
+
+    match (bytes.get(0), bytes.len().cmp(&addr.sun_path.len())) {
+        // Abstract paths don't need a null terminator
+        (Some(&0), Ordering::Greater) => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "path must be no longer than SUN_LEN"));
+        }
+        (_, Ordering::Greater) | (_, Ordering::Equal) => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "path must be shorter than SUN_LEN"));
+        }
+        _ => {}
+    }
+    for (dst, src) in addr.sun_path.iter_mut().zip(bytes.iter()) {
+        *dst = *src as libc::c_char;
+    }
+    // null byte for pathname addresses is already there because we zeroed the
+    // struct
+
+    let mut len = sun_path_offset() + bytes.len();
+    match bytes.get(0) {
+        Some(&0) | None => {}
+        Some(_) => len += 1,
     }
+    Ok((addr, len as libc::socklen_t))
 }
 
-impl IntoRawFd for UnixStream {
-    fn into_raw_fd(self) -> RawFd {
-        let fd = self.inner.0;
-        mem::forget(self);
-        fd
+// Returns whether `path` names a socket file, without following symlinks.
+fn is_socket_file(path: &Path) -> bool {
+    match fs::symlink_metadata(path) {
+        Ok(meta) => meta.file_type().is_socket(),
+        Err(_) => false,
     }
 }
 
+enum AddressKind<'a> {
+    Unnamed,
+    Pathname(&'a Path),
+    Abstract(&'a [u8]),
+}
 
-/// A structure representing a Unix domain seqpacket socket server.
-///
-/// # Examples
+/// An address associated with a Unix socket.
 ///
-/// ```rust,no_run
-/// use std::thread;
-/// use unix_socket::{UnixSeqpacket, UnixSeqpacketListener};
-///
-/// fn handle_client(_stream: UnixSeqpacket) {
-///     // ...
-/// }
-///
-/// let listener = UnixSeqpacketListener::bind("/path/to/the/socket").unwrap();
-///
-/// // accept connections and process them, spawning a new thread for each one
-/// for sock in listener.incoming() {
-///     match sock {
-///         Ok(sock) => {
-///             /* connection succeeded */
-///             thread::spawn(|| handle_client(sock));
-///         }
-///         Err(_err) => {
-///             /* connection failed */
-///             break;
-///         }
-///     }
-/// }
-///
-/// // close the listener socket
-/// drop(listener);
-/// ```
-pub struct UnixSeqpacketListener {
-    inner: Inner,
+/// `SocketAddr` is plain data, so it is `Send` and `Sync` and may be freely
+/// moved between and shared across threads.
+#[derive(Clone)]
+pub struct SocketAddr {
+    addr: libc::sockaddr_un,
+    len: libc::socklen_t,
 }
 
-impl fmt::Debug for UnixSeqpacketListener {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let mut builder = fmt.debug_struct("UnixSeqpacketListener");
-        builder.field("fd", &self.inner.0);
-        if let Ok(addr) = self.local_addr() {
-            builder.field("local", &addr);
+impl SocketAddr {
+    fn new<F>(f: F) -> io::Result<SocketAddr>
+        where F: FnOnce(*mut libc::sockaddr, *mut libc::socklen_t) -> libc::c_int
+    {
+        unsafe {
+            let mut addr: libc::sockaddr_un = mem::zeroed();
+            let mut len = mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+            try!(cvt(f(&mut addr as *mut _ as *mut _, &mut len)));
+
+            if len == 0 {
+                // When there is a datagram from unnamed unix socket
+                // linux returns zero bytes of address
+                len = sun_path_offset() as libc::socklen_t;  // i.e. zero-length address
+            } else if addr.sun_family != libc::AF_UNIX as libc::sa_family_t {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "file descriptor did not correspond to a Unix socket"));
+            }
+
+            Ok(SocketAddr {
+                addr: addr,
+                len: len,
+            })
         }
-        builder.finish()
     }
-}
-
-impl UnixSeqpacketListener {
-    /// Creates a new `UnixSeqpacketListener` bound to the specified socket.
-    ///
-    /// Linux provides, as a nonportable extension, a separate "abstract"
-    /// address namespace as opposed to filesystem-based addressing. If `path`
-    /// begins with a null byte, it will be interpreted as an "abstract"
-    /// address. Otherwise, it will be interpreted as a "pathname" address,
-    /// corresponding to a path on the filesystem.
-    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<UnixSeqpacketListener> {
-        unsafe {
-            let inner = try!(Inner::new(libc::SOCK_SEQPACKET));
-            let (addr, len) = try!(sockaddr_un(path));
 
-            try!(cvt(libc::bind(inner.0, &addr as *const _ as *const _, len)));
-            try!(cvt(libc::listen(inner.0, 128)));
+    /// Returns true iff the address is unnamed.
+    pub fn is_unnamed(&self) -> bool {
+        if let AddressKind::Unnamed = self.address() {
+            true
+        } else {
+            false
+        }
+    }
 
-            Ok(UnixSeqpacketListener { inner: inner })
+    /// Returns the contents of this address if it is a `pathname` address.
+    pub fn as_pathname(&self) -> Option<&Path> {
+        if let AddressKind::Pathname(path) = self.address() {
+            Some(path)
+        } else {
+            None
         }
     }
 
-    /// Accepts a new incoming connection to this listener.
+    /// Returns `true` if this is a pathname address and a file exists at
+    /// the path.
     ///
-    /// This function will block the calling thread until a new Unix connection
-    /// is established. When established, the corersponding `UnixSeqpacket` and
-    /// the remote peer's address will be returned.
-    pub fn accept(&self) -> io::Result<(UnixSeqpacket, SocketAddr)> {
-        unsafe {
-            let mut fd = 0;
-            let addr = try!(SocketAddr::new(|addr, len| {
-                fd = libc::accept(self.inner.0, addr, len);
-                fd
-            }));
+    /// This allows a clearer error to be reported than the `NotFound` from
+    /// a failed connection. It does not check that the file is a socket, and
+    /// the file may of course be created or removed right afterwards.
+    /// Abstract and unnamed addresses always return `false`.
+    pub fn exists(&self) -> bool {
+        match self.as_pathname() {
+            Some(path) => path.exists(),
+            None => false,
+        }
+    }
 
-            Ok((UnixSeqpacket { inner: Inner(fd) }, addr))
+    /// Returns the contents of this address (without the leading null byte)
+    /// if it is an `abstract` address.
+    ///
+    /// Abstract addresses are a Linux extension, so this always returns
+    /// `None` on other platforms.
+    pub fn abstract_name(&self) -> Option<&[u8]> {
+        if let AddressKind::Abstract(name) = self.address() {
+            Some(name)
+        } else {
+            None
         }
     }
 
-    /// Creates a new independently owned handle to the underlying socket.
+    /// Provides scoped access to the raw `sockaddr` and its length.
     ///
-    /// The returned `UnixSeqpacketListener` is a reference to the same socket that this
-    /// object references. Both handles can be used to accept incoming
-    /// connections and options set on one listener will affect the other.
-    pub fn try_clone(&self) -> io::Result<UnixSeqpacketListener> {
-        Ok(UnixSeqpacketListener { inner: try!(self.inner.try_clone()) })
+    /// This is intended for passing the address to C libraries. The pointer
+    /// is only valid for the duration of the call to `f`.
+    pub fn with_sockaddr<F, R>(&self, f: F) -> R
+        where F: FnOnce(*const libc::sockaddr, libc::socklen_t) -> R
+    {
+        f(&self.addr as *const _ as *const _, self.len)
     }
 
-    /// Returns the local socket address of this listener.
-    pub fn local_addr(&self) -> io::Result<SocketAddr> {
-        SocketAddr::new(|addr, len| unsafe { libc::getsockname(self.inner.0, addr, len) })
+    // Validates an explicit address length for use with bind or connect.
+    fn explicit_len(&self, len: usize) -> io::Result<libc::socklen_t> {
+        if len < sun_path_offset() || len > mem::size_of::<libc::sockaddr_un>() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "address length out of range"));
+        }
+        Ok(len as libc::socklen_t)
     }
 
-    /// Moves the socket into or out of nonblocking mode.
-    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
-        self.inner.set_nonblocking(nonblocking)
+    fn address<'a>(&'a self) -> AddressKind<'a> {
+        let len = self.len as usize - sun_path_offset();
+        let path = unsafe { mem::transmute::<&[libc::c_char], &[u8]>(&self.addr.sun_path) };
+
+        // OSX seems to return a len of 16 and a zeroed sun_path for unnamed addresses
+        if len == 0 || (cfg!(not(target_os = "linux")) && self.addr.sun_path[0] == 0) {
+            AddressKind::Unnamed
+        } else if self.addr.sun_path[0] == 0 {
+            AddressKind::Abstract(&path[1..len])
+        } else {
+            AddressKind::Pathname(OsStr::from_bytes(&path[..len - 1]).as_ref())
+        }
     }
+}
 
-    /// Returns the value of the `SO_ERROR` option.
-    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
-        self.inner.take_error()
+impl fmt::Debug for SocketAddr {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.address() {
+            AddressKind::Unnamed => write!(fmt, "(unnamed)"),
+            AddressKind::Abstract(name) => write!(fmt, "{} (abstract)", AsciiEscaped(name)),
+            AddressKind::Pathname(path) => write!(fmt, "{:?} (pathname)", path),
+        }
     }
+}
 
-    /// Returns an iterator over incoming connections.
-    ///
-    /// The iterator will never return `None` and will also not yield the
-    /// peer's `SocketAddr` structure.
-    pub fn incoming<'a>(&'a self) -> IncomingSeqpacket<'a> {
-        IncomingSeqpacket { listener: self }
+/// A Unix socket address with its namespace chosen explicitly.
+///
+/// The constructors taking an `AsRef<Path>` treat a path beginning with a
+/// null byte as an abstract address. `Address` makes the choice explicit
+/// instead, and is accepted by the `connect_address` and `bind_address`
+/// constructors of each socket type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Address {
+    /// A path in the filesystem. It must not begin with a null byte.
+    Pathname(PathBuf),
+    /// A name in Linux's abstract namespace, without the leading null byte.
+    Abstract(Vec<u8>),
+    /// No address. Binding to it asks the kernel to choose an abstract
+    /// address, which is only supported on Linux; it can't be connected to.
+    Unnamed,
+}
+
+impl From<PathBuf> for Address {
+    fn from(path: PathBuf) -> Address {
+        Address::Pathname(path)
     }
 }
 
-impl AsRawFd for UnixSeqpacketListener {
-    fn as_raw_fd(&self) -> RawFd {
-        self.inner.0
+impl<'a> From<&'a Path> for Address {
+    fn from(path: &'a Path) -> Address {
+        Address::Pathname(path.to_path_buf())
     }
 }
 
-impl FromRawFd for UnixSeqpacketListener {
-    unsafe fn from_raw_fd(fd: RawFd) -> UnixSeqpacketListener {
-        UnixSeqpacketListener { inner: Inner(fd) }
+impl Address {
+    fn sockaddr(&self) -> io::Result<(libc::sockaddr_un, libc::socklen_t)> {
+        match *self {
+            Address::Pathname(ref path) => {
+                match path.as_os_str().as_bytes().get(0) {
+                    Some(&0) => {
+                        Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           "pathname must not begin with a null byte"))
+                    }
+                    None => Err(io::Error::new(io::ErrorKind::InvalidInput, "pathname is empty")),
+                    Some(_) => unsafe { sockaddr_un(path) },
+                }
+            }
+            Address::Abstract(ref name) => unsafe {
+                let mut addr: libc::sockaddr_un = mem::zeroed();
+                addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+                if name.len() >= addr.sun_path.len() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                              "abstract name must be shorter than SUN_LEN"));
+                }
+                for (dst, src) in addr.sun_path[1..].iter_mut().zip(name.iter()) {
+                    *dst = *src as libc::c_char;
+                }
+                let len = sun_path_offset() + 1 + name.len();
+                Ok((addr, len as libc::socklen_t))
+            },
+            Address::Unnamed => unsafe {
+                let mut addr: libc::sockaddr_un = mem::zeroed();
+                addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+                Ok((addr, sun_path_offset() as libc::socklen_t))
+            },
+        }
     }
 }
 
-impl IntoRawFd for UnixSeqpacketListener {
-    fn into_raw_fd(self) -> RawFd {
-        let fd = self.inner.0;
-        mem::forget(self);
-        fd
+struct AsciiEscaped<'a>(&'a [u8]);
+
+impl<'a> fmt::Display for AsciiEscaped<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(fmt, "\""));
+        for byte in self.0.iter().cloned().flat_map(ascii::escape_default) {
+            try!(write!(fmt, "{}", byte as char));
+        }
+        write!(fmt, "\"")
     }
 }
 
-impl<'a> IntoIterator for &'a UnixSeqpacketListener {
-    type Item = io::Result<UnixSeqpacket>;
-    type IntoIter = IncomingSeqpacket<'a>;
+/// A control message received alongside data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlMessage {
+    /// File descriptors passed by the sender with `SCM_RIGHTS`.
+    ///
+    /// The receiver owns these descriptors and is responsible for closing
+    /// them.
+    Rights(Vec<RawFd>),
+    /// The credentials of the sending process, attached with
+    /// `SCM_CREDENTIALS`.
+    #[cfg(target_os = "linux")]
+    Credentials(os::linux::UCred),
+    /// The time at which the kernel received the message, attached with
+    /// `SCM_TIMESTAMP` when `SO_TIMESTAMP` is enabled.
+    #[cfg(target_os = "linux")]
+    Timestamp(SystemTime),
+    /// A control message of a kind not otherwise modeled, with its raw
+    /// payload.
+    Other {
+        /// The originating protocol, `cmsg_level`.
+        level: libc::c_int,
+        /// The protocol-specific type, `cmsg_type`.
+        cmsg_type: libc::c_int,
+        /// The message payload.
+        data: Vec<u8>,
+    },
+}
 
-    fn into_iter(self) -> IncomingSeqpacket<'a> {
-        self.incoming()
+/// Ancillary data received alongside a message.
+#[derive(Debug, Clone, Default)]
+pub struct Ancillary {
+    messages: Vec<ControlMessage>,
+    truncated: bool,
+}
+
+impl Ancillary {
+    fn parse(control: &[u8], flags: libc::c_int) -> Ancillary {
+        let header_len = unsafe { libc::CMSG_LEN(0) as usize };
+        let mut messages = vec![];
+        let mut off = 0;
+
+        while off + header_len <= control.len() {
+            let cmsg = unsafe {
+                ptr::read_unaligned(control[off..].as_ptr() as *const libc::cmsghdr)
+            };
+            let len = cmsg.cmsg_len as usize;
+            if len < header_len || off + len > control.len() {
+                break;
+            }
+            let data = &control[off + header_len..off + len];
+
+            match (cmsg.cmsg_level, cmsg.cmsg_type) {
+                (libc::SOL_SOCKET, libc::SCM_RIGHTS) => {
+                    let fds = data.chunks(mem::size_of::<RawFd>())
+                                  .filter(|c| c.len() == mem::size_of::<RawFd>())
+                                  .map(|c| unsafe { ptr::read_unaligned(c.as_ptr() as *const RawFd) })
+                                  .collect();
+                    messages.push(ControlMessage::Rights(fds));
+                }
+                #[cfg(target_os = "linux")]
+                (libc::SOL_SOCKET, libc::SCM_CREDENTIALS) if data.len() >= mem::size_of::<libc::ucred>() => {
+                    let cred = unsafe { ptr::read_unaligned(data.as_ptr() as *const libc::ucred) };
+                    messages.push(ControlMessage::Credentials(os::linux::UCred {
+                        pid: cred.pid,
+                        uid: cred.uid,
+                        gid: cred.gid,
+                    }));
+                }
+                #[cfg(target_os = "linux")]
+                (libc::SOL_SOCKET, libc::SCM_TIMESTAMP) if data.len() >= mem::size_of::<libc::timeval>() => {
+                    let tv = unsafe { ptr::read_unaligned(data.as_ptr() as *const libc::timeval) };
+                    let since_epoch = Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000);
+                    messages.push(ControlMessage::Timestamp(UNIX_EPOCH + since_epoch));
+                }
+                (level, cmsg_type) => {
+                    messages.push(ControlMessage::Other {
+                        level: level,
+                        cmsg_type: cmsg_type,
+                        data: data.to_vec(),
+                    });
+                }
+            }
+
+            off += cmsg_align(len);
+        }
+
+        Ancillary {
+            messages: messages,
+            truncated: flags & libc::MSG_CTRUNC != 0,
+        }
+    }
+
+    // Finds the credentials in `control` without allocating.
+    #[cfg(target_os = "linux")]
+    fn find_credentials(control: &[u8]) -> Option<os::linux::UCred> {
+        let header_len = unsafe { libc::CMSG_LEN(0) as usize };
+        let mut off = 0;
+
+        while off + header_len <= control.len() {
+            let cmsg = unsafe {
+                ptr::read_unaligned(control[off..].as_ptr() as *const libc::cmsghdr)
+            };
+            let len = cmsg.cmsg_len as usize;
+            if len < header_len || off + len > control.len() {
+                break;
+            }
+            if cmsg.cmsg_level == libc::SOL_SOCKET && cmsg.cmsg_type == libc::SCM_CREDENTIALS &&
+               len - header_len >= mem::size_of::<libc::ucred>() {
+                let cred = unsafe {
+                    ptr::read_unaligned(control[off + header_len..].as_ptr() as *const libc::ucred)
+                };
+                return Some(os::linux::UCred {
+                    pid: cred.pid,
+                    uid: cred.uid,
+                    gid: cred.gid,
+                });
+            }
+
+            off += cmsg_align(len);
+        }
+
+        None
+    }
+
+    /// Parses the control messages in `control`.
+    ///
+    /// `control` should be the prefix of a control buffer written by a
+    /// receive, such as the length reported by
+    /// `UnixDatagram::recv_with_control`.
+    pub fn from_control(control: &[u8]) -> Ancillary {
+        Ancillary::parse(control, 0)
+    }
+
+    /// Returns an iterator over the received control messages, in the order
+    /// the kernel delivered them.
+    pub fn messages<'a>(&'a self) -> slice::Iter<'a, ControlMessage> {
+        self.messages.iter()
+    }
+
+    /// Returns all file descriptors received with `SCM_RIGHTS`.
+    pub fn fds(&self) -> Vec<RawFd> {
+        let mut fds = vec![];
+        for message in &self.messages {
+            if let ControlMessage::Rights(ref rights) = *message {
+                fds.extend_from_slice(rights);
+            }
+        }
+        fds
+    }
+
+    /// Returns the sender's credentials, if they were received.
+    #[cfg(target_os = "linux")]
+    pub fn credentials(&self) -> Option<&os::linux::UCred> {
+        self.messages.iter().filter_map(|message| {
+            match *message {
+                ControlMessage::Credentials(ref cred) => Some(cred),
+                _ => None,
+            }
+        }).next()
+    }
+
+    /// Returns the kernel receive timestamp, if it was received.
+    #[cfg(target_os = "linux")]
+    pub fn timestamp(&self) -> Option<SystemTime> {
+        self.messages.iter().filter_map(|message| {
+            match *message {
+                ControlMessage::Timestamp(time) => Some(time),
+                _ => None,
+            }
+        }).next()
+    }
+
+    /// Returns `true` if some control data was discarded because it did not
+    /// fit in the receive buffer.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
     }
 }
 
-/// An iterator over incoming connections to a `UnixSeqpacketListener`.
+fn cmsg_align(len: usize) -> usize {
+    unsafe { (libc::CMSG_SPACE(len as _) - libc::CMSG_SPACE(0)) as usize }
+}
+
+/// Returns `true` if `err` represents a transient condition under which the
+/// failed operation may reasonably be retried.
 ///
-/// It will never return `None`.
-#[derive(Debug)]
-pub struct IncomingSeqpacket<'a> {
-    listener: &'a UnixSeqpacketListener,
+/// This covers `EAGAIN`/`EWOULDBLOCK` and `EINTR`, as well as `ENOBUFS`, which
+/// datagram sends may report while the system is under memory pressure.
+pub fn is_transient(err: &io::Error) -> bool {
+    match err.raw_os_error() {
+        Some(libc::ENOBUFS) | Some(libc::EAGAIN) | Some(libc::EINTR) => true,
+        Some(errno) => errno == libc::EWOULDBLOCK,
+        None => false,
+    }
 }
 
-impl<'a> Iterator for IncomingSeqpacket<'a> {
-    type Item = io::Result<UnixSeqpacket>;
+/// Returns `true` if `err` is `EPIPE`, reported when writing to a socket
+/// whose peer has closed or shut down its read half.
+pub fn is_broken_pipe(err: &io::Error) -> bool {
+    err.raw_os_error() == Some(libc::EPIPE)
+}
 
-    fn next(&mut self) -> Option<io::Result<UnixSeqpacket>> {
-        Some(self.listener.accept().map(|s| s.0))
-    }
+/// Returns `true` if `err` is `ECONNRESET`, reported when the peer closed the
+/// connection while data was still pending.
+pub fn is_connection_reset(err: &io::Error) -> bool {
+    err.raw_os_error() == Some(libc::ECONNRESET)
+}
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        (usize::max_value(), None)
+/// Returns `true` if `err` is `EAGAIN`/`EWOULDBLOCK`, reported when an
+/// operation on a nonblocking socket cannot complete immediately.
+pub fn is_would_block(err: &io::Error) -> bool {
+    match err.raw_os_error() {
+        Some(errno) => errno == libc::EAGAIN || errno == libc::EWOULDBLOCK,
+        None => false,
     }
 }
 
+/// Returns `true` if `err` is `ENOTCONN`, reported when an operation requires
+/// a connected socket.
+pub fn is_not_connected(err: &io::Error) -> bool {
+    err.raw_os_error() == Some(libc::ENOTCONN)
+}
 
+/// Returns the address family of the socket referred to by `fd`.
+///
+/// This is useful for confirming that an adopted file descriptor is an
+/// `AF_UNIX` socket before wrapping it.
+#[cfg(target_os = "linux")]
+pub fn domain(fd: RawFd) -> io::Result<libc::c_int> {
+    getsockopt(fd, libc::SOL_SOCKET, libc::SO_DOMAIN)
+}
 
-/// A structure representing a Unix domain stream socket server.
+/// Returns the address family of the socket referred to by `fd`.
 ///
-/// # Examples
+/// This is useful for confirming that an adopted file descriptor is an
+/// `AF_UNIX` socket before wrapping it.
+#[cfg(not(target_os = "linux"))]
+pub fn domain(fd: RawFd) -> io::Result<libc::c_int> {
+    unsafe {
+        let mut addr: libc::sockaddr_storage = mem::zeroed();
+        let mut len = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        try!(cvt(libc::getsockname(fd, &mut addr as *mut _ as *mut _, &mut len)));
+        Ok(addr.ss_family as libc::c_int)
+    }
+}
+
+/// Returns `true` if the socket referred to by `fd` is listening for
+/// connections.
 ///
-/// ```rust,no_run
-/// use std::thread;
-/// use unix_socket::{UnixStream, UnixListener};
+/// This allows code handed an arbitrary socket to decide between `accept`
+/// and `recv`.
+#[cfg(target_os = "linux")]
+pub fn is_listening(fd: RawFd) -> io::Result<bool> {
+    getsockopt::<libc::c_int>(fd, libc::SOL_SOCKET, libc::SO_ACCEPTCONN).map(|v| v != 0)
+}
+
+/// A set of options to apply to a socket.
 ///
-/// fn handle_client(_stream: UnixStream) {
-///     // ...
-/// }
+/// Each option left as `None` is not changed.
+#[derive(Debug, Clone, Default)]
+pub struct SocketConfig {
+    /// The read timeout.
+    pub read_timeout: Option<Duration>,
+    /// The write timeout.
+    pub write_timeout: Option<Duration>,
+    /// The size of the send buffer.
+    pub send_buffer_size: Option<usize>,
+    /// The size of the receive buffer.
+    pub recv_buffer_size: Option<usize>,
+    /// Whether the socket is in nonblocking mode.
+    pub nonblocking: Option<bool>,
+    /// Whether the socket's file descriptor is closed on `exec`.
+    pub cloexec: Option<bool>,
+}
+
+/// An error applying a `SocketConfig`, identifying the option which could
+/// not be set.
 ///
-/// let listener = UnixListener::bind("/path/to/the/socket").unwrap();
+/// It converts into an `io::Error` of the same kind as `source`.
+#[derive(Debug)]
+pub struct ConfigError {
+    /// The name of the `SocketConfig` field which could not be applied.
+    pub option: &'static str,
+    /// The error returned when setting the option.
+    pub source: io::Error,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "failed to set {}: {}", self.option, self.source)
+    }
+}
+
+impl error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<ConfigError> for io::Error {
+    fn from(err: ConfigError) -> io::Error {
+        io::Error::new(err.source.kind(), err)
+    }
+}
+
+/// The type of a socket, as reported by the kernel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketType {
+    /// A `SOCK_STREAM` socket.
+    Stream,
+    /// A `SOCK_DGRAM` socket.
+    Datagram,
+    /// A `SOCK_SEQPACKET` socket.
+    Seqpacket,
+}
+
+/// The readiness of a socket for I/O, as reported by `poll`.
 ///
-/// // accept connections and process them, spawning a new thread for each one
-/// for stream in listener.incoming() {
-///     match stream {
-///         Ok(stream) => {
-///             /* connection succeeded */
-///             thread::spawn(|| handle_client(stream));
-///         }
-///         Err(_err) => {
-///             /* connection failed */
-///             break;
-///         }
-///     }
-/// }
+/// Along with readability and writability, this reports the error, hang up
+/// and invalid descriptor conditions, which `poll` always reports whether or
+/// not they were asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ready(libc::c_short);
+
+impl Ready {
+    /// Returns `true` if a read would not block.
+    pub fn is_readable(&self) -> bool {
+        self.0 & libc::POLLIN != 0
+    }
+
+    /// Returns `true` if a write would not block.
+    pub fn is_writable(&self) -> bool {
+        self.0 & libc::POLLOUT != 0
+    }
+
+    /// Returns `true` if an error is pending on the socket (`POLLERR`).
+    ///
+    /// The error can be retrieved with `take_error`.
+    pub fn is_error(&self) -> bool {
+        self.0 & libc::POLLERR != 0
+    }
+
+    /// Returns `true` if the connection has been hung up (`POLLHUP`).
+    ///
+    /// Data queued before the hang up may still be read.
+    pub fn is_hup(&self) -> bool {
+        self.0 & libc::POLLHUP != 0
+    }
+
+    /// Returns `true` if the file descriptor is not open (`POLLNVAL`).
+    pub fn is_invalid(&self) -> bool {
+        self.0 & libc::POLLNVAL != 0
+    }
+}
+
+/// OS specific extension traits.
+pub mod os {
+    /// Linux specific extension traits.
+    #[cfg(target_os = "linux")]
+    pub mod linux {
+        use libc;
+        use std::ffi::OsStr;
+        use std::io;
+        use std::os::unix::ffi::OsStrExt;
+        use std::time::SystemTime;
+
+        use {Ancillary, SocketAddr, UnixDatagram, UnixSeqpacket, UnixStream};
+
+        /// Linux specific extensions for the `SocketAddr` type.
+        pub trait SocketAddrExt {
+            /// Returns the contents of this address (without the leading
+            /// null byte) if it is an `abstract` address.
+            fn as_abstract(&self) -> Option<&[u8]>;
+
+            /// Returns the contents of this address (without the leading
+            /// null byte) as an `OsStr` if it is an `abstract` address.
+            ///
+            /// This is convenient for displaying abstract addresses alongside
+            /// pathname addresses.
+            fn as_abstract_os(&self) -> Option<&OsStr>;
+        }
+
+        impl SocketAddrExt for SocketAddr {
+            fn as_abstract(&self) -> Option<&[u8]> {
+                self.abstract_name()
+            }
+
+            fn as_abstract_os(&self) -> Option<&OsStr> {
+                self.as_abstract().map(OsStr::from_bytes)
+            }
+        }
+
+        /// The credentials of a process on the other end of a Unix socket.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct UCred {
+            /// The process ID.
+            pub pid: libc::pid_t,
+            /// The user ID.
+            pub uid: libc::uid_t,
+            /// The group ID.
+            pub gid: libc::gid_t,
+        }
+
+        /// Linux specific extensions for `UnixDatagram`.
+        pub trait UnixDatagramExt {
+            /// Sets the value of the `SO_TIMESTAMP` option.
+            ///
+            /// When enabled, the time at which the kernel received each
+            /// datagram is attached to it as ancillary data. The timestamp is
+            /// taken from the system realtime clock (`CLOCK_REALTIME`), the
+            /// same clock that backs `SystemTime`.
+            fn set_timestamping(&self, timestamping: bool) -> io::Result<()>;
+
+            /// Returns the value of the `SO_TIMESTAMP` option.
+            fn timestamping(&self) -> io::Result<bool>;
+
+            /// Receives data from the socket along with its kernel receive
+            /// timestamp.
+            ///
+            /// Timestamping must have been enabled with `set_timestamping`
+            /// before the datagram arrived; otherwise an error is returned and
+            /// the datagram is discarded.
+            ///
+            /// On success, returns the number of bytes read, the address from
+            /// whence the data came, and the time it was received.
+            fn recv_from_timestamped(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, SystemTime)>;
+
+            /// Receives data from the socket along with the sender's
+            /// credentials, without allocating.
+            ///
+            /// The message's ancillary data is received into `control`,
+            /// which should have room for at least
+            /// `CMSG_SPACE(size_of::<ucred>())` bytes. Credentials are only
+            /// attached when `SO_PASSCRED` is enabled on this socket (see
+            /// `UnixSocketExt::set_passcred`); otherwise `None` is returned
+            /// for them.
+            ///
+            /// On success, returns the number of bytes read, the address
+            /// from whence the data came, and the sender's credentials.
+            fn recv_from_creds(&self,
+                               buf: &mut [u8],
+                               control: &mut [u8])
+                               -> io::Result<(usize, SocketAddr, Option<UCred>)>;
+        }
+
+        impl UnixDatagramExt for UnixDatagram {
+            fn set_timestamping(&self, timestamping: bool) -> io::Result<()> {
+                self.inner.setsockopt(libc::SOL_SOCKET, libc::SO_TIMESTAMP, timestamping as libc::c_int)
+            }
+
+            fn timestamping(&self) -> io::Result<bool> {
+                self.inner.getsockopt::<libc::c_int>(libc::SOL_SOCKET, libc::SO_TIMESTAMP).map(|v| v != 0)
+            }
+
+            fn recv_from_timestamped(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, SystemTime)> {
+                let (count, addr, ancillary) = try!(self.recv_from_ancillary(buf));
+                match ancillary.timestamp() {
+                    Some(time) => Ok((count, addr, time)),
+                    None => Err(io::Error::new(io::ErrorKind::InvalidData,
+                                               "datagram did not carry a receive timestamp")),
+                }
+            }
+
+            fn recv_from_creds(&self,
+                               buf: &mut [u8],
+                               control: &mut [u8])
+                               -> io::Result<(usize, SocketAddr, Option<UCred>)> {
+                let (count, control_len, addr) = try!(self.recv_with_control(buf, control));
+                Ok((count, addr, Ancillary::find_credentials(&control[..control_len])))
+            }
+        }
+
+        /// Linux specific extensions for the Unix socket types.
+        pub trait UnixSocketExt {
+            /// Sets the value of the `SO_PASSCRED` option.
+            ///
+            /// When enabled, the credentials of the sending process are
+            /// received as ancillary data with each message.
+            fn set_passcred(&self, passcred: bool) -> io::Result<()>;
+
+            /// Returns the value of the `SO_PASSCRED` option.
+            fn passcred(&self) -> io::Result<bool>;
+
+            /// Sets the size of the socket's send buffer, ignoring the system
+            /// `wmem_max` limit.
+            ///
+            /// This requires the `CAP_NET_ADMIN` capability; an `EPERM` error
+            /// is returned otherwise.
+            fn set_send_buffer_size_force(&self, size: usize) -> io::Result<()>;
+
+            /// Sets the size of the socket's receive buffer, ignoring the
+            /// system `rmem_max` limit.
+            ///
+            /// This requires the `CAP_NET_ADMIN` capability; an `EPERM` error
+            /// is returned otherwise.
+            fn set_recv_buffer_size_force(&self, size: usize) -> io::Result<()>;
+
+            /// Sets the value of the `SO_PRIORITY` option.
+            ///
+            /// The priority is visible to traffic control and eBPF programs
+            /// classifying the socket's traffic. Priorities outside of 0 to 6
+            /// require the `CAP_NET_ADMIN` capability; an `EPERM` error is
+            /// returned otherwise.
+            fn set_priority(&self, priority: u32) -> io::Result<()>;
+
+            /// Returns the value of the `SO_PRIORITY` option.
+            fn priority(&self) -> io::Result<u32>;
+
+            /// Receives a message from the socket's error queue.
+            ///
+            /// This calls `recvmsg` with `MSG_ERRQUEUE`, which is how
+            /// `SO_TIMESTAMPING` completions and extended error reports are
+            /// collected. The message's ancillary data is written to
+            /// `control` for the caller to parse. This never blocks; if the
+            /// queue is empty an error of kind `WouldBlock` is returned. Only
+            /// some protocols populate the error queue, and Unix sockets
+            /// themselves currently never do, so this is mainly useful on
+            /// descriptors shared with other code.
+            ///
+            /// On success, returns the number of bytes of data and of
+            /// ancillary data read.
+            fn recv_errqueue(&self, buf: &mut [u8], control: &mut [u8]) -> io::Result<(usize, usize)>;
+
+            /// Returns the group IDs of the peer process.
+            ///
+            /// The peer's primary group, as reported by `SO_PEERCRED`, comes
+            /// first, followed by its supplementary groups, which are read
+            /// from `/proc/<pid>/status` using the pid `SO_PEERCRED` reports.
+            ///
+            /// This is best-effort. The supplementary groups are read at the
+            /// time of the call rather than when the connection was made, and
+            /// the peer process must still exist and be visible in this
+            /// process's pid namespace. To guard against the pid having been
+            /// reused, an error of kind `NotFound` is returned if the process
+            /// found doesn't have the peer's user ID, but a process with the
+            /// same user ID can't be told apart.
+            fn peer_groups(&self) -> io::Result<Vec<u32>>;
+        }
+
+        impl UnixSocketExt for UnixStream {
+            fn set_send_buffer_size_force(&self, size: usize) -> io::Result<()> {
+                self.inner.set_buffer_size(size, libc::SO_SNDBUFFORCE)
+            }
+
+            fn set_recv_buffer_size_force(&self, size: usize) -> io::Result<()> {
+                self.inner.set_buffer_size(size, libc::SO_RCVBUFFORCE)
+            }
+
+            fn set_passcred(&self, passcred: bool) -> io::Result<()> {
+                self.inner.setsockopt(libc::SOL_SOCKET, libc::SO_PASSCRED, passcred as libc::c_int)
+            }
+
+            fn passcred(&self) -> io::Result<bool> {
+                self.inner.getsockopt::<libc::c_int>(libc::SOL_SOCKET, libc::SO_PASSCRED).map(|v| v != 0)
+            }
+
+            fn set_priority(&self, priority: u32) -> io::Result<()> {
+                self.inner.set_priority(priority)
+            }
+
+            fn priority(&self) -> io::Result<u32> {
+                self.inner.priority()
+            }
+
+            fn recv_errqueue(&self, buf: &mut [u8], control: &mut [u8]) -> io::Result<(usize, usize)> {
+                self.inner.recv_errqueue(buf, control)
+            }
+
+            fn peer_groups(&self) -> io::Result<Vec<u32>> {
+                self.inner.peer_groups()
+            }
+        }
+
+        impl UnixSocketExt for UnixDatagram {
+            fn set_send_buffer_size_force(&self, size: usize) -> io::Result<()> {
+                self.inner.set_buffer_size(size, libc::SO_SNDBUFFORCE)
+            }
+
+            fn set_recv_buffer_size_force(&self, size: usize) -> io::Result<()> {
+                self.inner.set_buffer_size(size, libc::SO_RCVBUFFORCE)
+            }
+
+            fn set_passcred(&self, passcred: bool) -> io::Result<()> {
+                self.inner.setsockopt(libc::SOL_SOCKET, libc::SO_PASSCRED, passcred as libc::c_int)
+            }
+
+            fn passcred(&self) -> io::Result<bool> {
+                self.inner.getsockopt::<libc::c_int>(libc::SOL_SOCKET, libc::SO_PASSCRED).map(|v| v != 0)
+            }
+
+            fn set_priority(&self, priority: u32) -> io::Result<()> {
+                self.inner.set_priority(priority)
+            }
+
+            fn priority(&self) -> io::Result<u32> {
+                self.inner.priority()
+            }
+
+            fn recv_errqueue(&self, buf: &mut [u8], control: &mut [u8]) -> io::Result<(usize, usize)> {
+                self.inner.recv_errqueue(buf, control)
+            }
+
+            fn peer_groups(&self) -> io::Result<Vec<u32>> {
+                self.inner.peer_groups()
+            }
+        }
+
+        impl UnixSocketExt for UnixSeqpacket {
+            fn set_send_buffer_size_force(&self, size: usize) -> io::Result<()> {
+                self.inner.set_buffer_size(size, libc::SO_SNDBUFFORCE)
+            }
+
+            fn set_recv_buffer_size_force(&self, size: usize) -> io::Result<()> {
+                self.inner.set_buffer_size(size, libc::SO_RCVBUFFORCE)
+            }
+
+            fn set_passcred(&self, passcred: bool) -> io::Result<()> {
+                self.inner.setsockopt(libc::SOL_SOCKET, libc::SO_PASSCRED, passcred as libc::c_int)
+            }
+
+            fn passcred(&self) -> io::Result<bool> {
+                self.inner.getsockopt::<libc::c_int>(libc::SOL_SOCKET, libc::SO_PASSCRED).map(|v| v != 0)
+            }
+
+            fn set_priority(&self, priority: u32) -> io::Result<()> {
+                self.inner.set_priority(priority)
+            }
+
+            fn priority(&self) -> io::Result<u32> {
+                self.inner.priority()
+            }
+
+            fn recv_errqueue(&self, buf: &mut [u8], control: &mut [u8]) -> io::Result<(usize, usize)> {
+                self.inner.recv_errqueue(buf, control)
+            }
+
+            fn peer_groups(&self) -> io::Result<Vec<u32>> {
+                self.inner.peer_groups()
+            }
+        }
+    }
+}
+
+/// Length-prefixed message framing over stream sockets.
+pub mod framed {
+    use libc;
+    use std::io::{self, Read, Write};
+    use std::os::unix::io::RawFd;
+
+    use UnixStream;
+
+    // The default limit on the size of a frame.
+    const DEFAULT_MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+    /// The encoding of the length which precedes each frame.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum LengthPrefix {
+        /// A big endian `u16`.
+        U16BigEndian,
+        /// A little endian `u16`.
+        U16LittleEndian,
+        /// A big endian `u32`.
+        U32BigEndian,
+        /// A little endian `u32`.
+        U32LittleEndian,
+    }
+
+    impl LengthPrefix {
+        fn len(&self) -> usize {
+            match *self {
+                LengthPrefix::U16BigEndian | LengthPrefix::U16LittleEndian => 2,
+                LengthPrefix::U32BigEndian | LengthPrefix::U32LittleEndian => 4,
+            }
+        }
+
+        fn max_value(&self) -> u64 {
+            match *self {
+                LengthPrefix::U16BigEndian | LengthPrefix::U16LittleEndian => u16::MAX as u64,
+                LengthPrefix::U32BigEndian | LengthPrefix::U32LittleEndian => u32::MAX as u64,
+            }
+        }
+
+        fn encode(&self, len: usize, buf: &mut [u8; 4]) -> usize {
+            match *self {
+                LengthPrefix::U16BigEndian => buf[..2].copy_from_slice(&(len as u16).to_be_bytes()),
+                LengthPrefix::U16LittleEndian => buf[..2].copy_from_slice(&(len as u16).to_le_bytes()),
+                LengthPrefix::U32BigEndian => buf.copy_from_slice(&(len as u32).to_be_bytes()),
+                LengthPrefix::U32LittleEndian => buf.copy_from_slice(&(len as u32).to_le_bytes()),
+            }
+            self.len()
+        }
+
+        fn decode(&self, buf: &[u8; 4]) -> u64 {
+            match *self {
+                LengthPrefix::U16BigEndian => u16::from_be_bytes([buf[0], buf[1]]) as u64,
+                LengthPrefix::U16LittleEndian => u16::from_le_bytes([buf[0], buf[1]]) as u64,
+                LengthPrefix::U32BigEndian => u32::from_be_bytes(*buf) as u64,
+                LengthPrefix::U32LittleEndian => u32::from_le_bytes(*buf) as u64,
+            }
+        }
+    }
+
+    /// A `UnixStream` which sends and receives length-prefixed frames.
+    ///
+    /// Each frame is written as its length followed by its payload. By
+    /// default the length is a big endian `u32` and frames are limited to
+    /// 16 MiB.
+    #[derive(Debug)]
+    pub struct FramedStream {
+        stream: UnixStream,
+        prefix: LengthPrefix,
+        max_frame_size: usize,
+    }
+
+    impl FramedStream {
+        /// Wraps a `UnixStream`.
+        pub fn new(stream: UnixStream) -> FramedStream {
+            FramedStream {
+                stream: stream,
+                prefix: LengthPrefix::U32BigEndian,
+                max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            }
+        }
+
+        /// Sets the encoding of the length preceding each frame.
+        ///
+        /// Both ends of the stream must use the same encoding.
+        pub fn set_length_prefix(&mut self, prefix: LengthPrefix) {
+            self.prefix = prefix;
+        }
+
+        /// Returns the encoding of the length preceding each frame.
+        pub fn length_prefix(&self) -> LengthPrefix {
+            self.prefix
+        }
+
+        /// Sets the maximum size of a frame's payload.
+        ///
+        /// Sending a larger frame fails with `InvalidInput`. Receiving a
+        /// frame which declares a larger length fails with `InvalidData`
+        /// before anything is allocated for it, and leaves the stream
+        /// positioned within the frame.
+        pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+            self.max_frame_size = max_frame_size;
+        }
+
+        /// Returns the maximum size of a frame's payload.
+        pub fn max_frame_size(&self) -> usize {
+            self.max_frame_size
+        }
+
+        /// Returns a reference to the underlying stream.
+        pub fn get_ref(&self) -> &UnixStream {
+            &self.stream
+        }
+
+        /// Consumes the `FramedStream`, returning the underlying stream.
+        pub fn into_inner(self) -> UnixStream {
+            self.stream
+        }
+
+        /// Sends `buf` as a single frame.
+        pub fn send_frame(&mut self, buf: &[u8]) -> io::Result<()> {
+            if buf.len() > self.max_frame_size || buf.len() as u64 > self.prefix.max_value() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame too large"));
+            }
+            let mut header = [0; 4];
+            let len = self.prefix.encode(buf.len(), &mut header);
+            try!(self.stream.write_all(&header[..len]));
+            self.stream.write_all(buf)
+        }
+
+        /// Receives a single frame.
+        ///
+        /// If the peer closes the stream before a complete frame has been
+        /// received, an error of kind `UnexpectedEof` is returned.
+        pub fn recv_frame(&mut self) -> io::Result<Vec<u8>> {
+            let mut header = [0; 4];
+            let len = self.prefix.len();
+            try!(self.stream.read_exact(&mut header[..len]));
+            let len = self.prefix.decode(&header);
+            if len > self.max_frame_size as u64 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          format!("frame of {} bytes exceeds the maximum of {} bytes",
+                                                  len,
+                                                  self.max_frame_size)));
+            }
+            let mut buf = vec![0; len as usize];
+            try!(self.stream.read_exact(&mut buf));
+            Ok(buf)
+        }
+
+        /// Sends `buf` as a single frame, passing `fds` to the peer along
+        /// with it.
+        ///
+        /// The descriptors are attached to the first byte of the frame with
+        /// `SCM_RIGHTS`, and remain open in this process. The peer must
+        /// receive the frame with `recv_frame_with_fds`, since `recv_frame`
+        /// discards them.
+        pub fn send_frame_with_fds(&mut self, buf: &[u8], fds: &[RawFd]) -> io::Result<()> {
+            if buf.len() > self.max_frame_size || buf.len() as u64 > self.prefix.max_value() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame too large"));
+            }
+            let mut header = [0; 4];
+            let len = self.prefix.encode(buf.len(), &mut header);
+            let sent = loop {
+                match self.stream.send_fds(&header[..len], fds) {
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    res => break try!(res),
+                }
+            };
+            try!(self.stream.write_all(&header[sent..len]));
+            self.stream.write_all(buf)
+        }
+
+        /// Receives a single frame along with any file descriptors passed
+        /// with it.
+        ///
+        /// Descriptors received with any part of the frame are returned,
+        /// and are owned by the caller. If an error occurs, any descriptors
+        /// received so far are closed. If the peer closes the stream before a
+        /// complete frame has been received, an error of kind
+        /// `UnexpectedEof` is returned.
+        pub fn recv_frame_with_fds(&mut self) -> io::Result<(Vec<u8>, Vec<RawFd>)> {
+            let mut fds = vec![];
+            match self.recv_frame_fds(&mut fds) {
+                Ok(buf) => Ok((buf, fds)),
+                Err(e) => {
+                    for fd in fds {
+                        unsafe {
+                            libc::close(fd);
+                        }
+                    }
+                    Err(e)
+                }
+            }
+        }
+
+        fn recv_frame_fds(&mut self, fds: &mut Vec<RawFd>) -> io::Result<Vec<u8>> {
+            let mut header = [0; 4];
+            let len = self.prefix.len();
+            try!(self.recv_exact_fds(&mut header[..len], fds));
+            let len = self.prefix.decode(&header);
+            if len > self.max_frame_size as u64 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          format!("frame of {} bytes exceeds the maximum of {} bytes",
+                                                  len,
+                                                  self.max_frame_size)));
+            }
+            let mut buf = vec![0; len as usize];
+            try!(self.recv_exact_fds(&mut buf, fds));
+            Ok(buf)
+        }
+
+        // Fills `buf`, collecting descriptors from every read, since they may
+        // arrive with any part of it.
+        fn recv_exact_fds(&self, buf: &mut [u8], fds: &mut Vec<RawFd>) -> io::Result<()> {
+            let mut filled = 0;
+            while filled < buf.len() {
+                match self.stream.recv_fds(&mut buf[filled..]) {
+                    Ok((0, _)) => {
+                        return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                                  "failed to fill whole buffer"))
+                    }
+                    Ok((count, received)) => {
+                        filled += count;
+                        fds.extend(received);
+                    }
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// A Unix stream socket.
 ///
-/// // close the listener socket
-/// drop(listener);
+/// # Examples
+///
+/// ```rust,no_run
+/// use unix_socket::UnixStream;
+/// use std::io::prelude::*;
+///
+/// let mut stream = UnixStream::connect("/path/to/my/socket").unwrap();
+/// stream.write_all(b"hello world").unwrap();
+/// let mut response = String::new();
+/// stream.read_to_string(&mut response).unwrap();
+/// println!("{}", response);
 /// ```
-pub struct UnixListener {
+pub struct UnixStream {
     inner: Inner,
+    id: u64,
+    created: Instant,
+}
+
+impl fmt::Debug for UnixStream {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut builder = fmt.debug_struct("UnixStream");
+        builder.field("fd", &self.inner.0);
+        if let Ok(flags) = self.inner.fd_flags() {
+            builder.field("cloexec", &(flags & libc::FD_CLOEXEC != 0));
+        }
+        if let Ok(accepting) = self.inner.accepting() {
+            builder.field("accepting", &accepting);
+        }
+        if let Ok(addr) = self.local_addr() {
+            builder.field("local", &addr);
+        }
+        if let Ok(addr) = self.peer_addr() {
+            builder.field("peer", &addr);
+        }
+        builder.finish()
+    }
 }
 
-impl fmt::Debug for UnixListener {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let mut builder = fmt.debug_struct("UnixListener");
-        builder.field("fd", &self.inner.0);
-        if let Ok(addr) = self.local_addr() {
-            builder.field("local", &addr);
+impl UnixStream {
+    fn from_inner(inner: Inner) -> UnixStream {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        UnixStream {
+            inner: inner,
+            id: NEXT_ID.fetch_add(1, atomic::Ordering::Relaxed),
+            created: Instant::now(),
+        }
+    }
+
+    /// Connects to the socket named by `path`.
+    ///
+    /// Linux provides, as a nonportable extension, a separate "abstract"
+    /// address namespace as opposed to filesystem-based addressing. If `path`
+    /// begins with a null byte, it will be interpreted as an "abstract"
+    /// address. Otherwise, it will be interpreted as a "pathname" address,
+    /// corresponding to a path on the filesystem.
+    ///
+    /// The socket is created with the close-on-exec flag set, so it is not
+    /// inherited by child processes.
+    pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<UnixStream> {
+        let inner = try!(Inner::new(libc::SOCK_STREAM));
+        try!(inner.connect(path));
+        Ok(UnixStream::from_inner(inner))
+    }
+
+    /// Connects to the socket named by `path`, applying `cfg` to the socket
+    /// before the connection is made.
+    ///
+    /// Some options, notably the buffer sizes, may only take full effect if
+    /// set before connecting.
+    pub fn connect_with<P: AsRef<Path>>(path: P, cfg: &SocketConfig) -> io::Result<UnixStream> {
+        let inner = try!(Inner::new(libc::SOCK_STREAM));
+        try!(inner.configure(cfg));
+        try!(inner.connect(path));
+        Ok(UnixStream::from_inner(inner))
+    }
+
+    /// Connects to the first of several candidate socket paths which accepts
+    /// the connection.
+    ///
+    /// Each path is tried in turn. A path which does not exist, or which
+    /// nothing is listening on, is skipped; any other error is returned
+    /// immediately. If no path accepts the connection, the error from the
+    /// last attempt is returned.
+    pub fn connect_any<I, P>(paths: I) -> io::Result<UnixStream>
+        where I: IntoIterator<Item = P>,
+              P: AsRef<Path>
+    {
+        let mut last_err = None;
+        for path in paths {
+            match UnixStream::connect(path) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => {
+                    match e.raw_os_error() {
+                        Some(libc::ENOENT) | Some(libc::ECONNREFUSED) => last_err = Some(e),
+                        _ => return Err(e),
+                    }
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no paths to connect to")
+        }))
+    }
+
+    /// Connects to the socket named by `path`, leaving the close-on-exec flag
+    /// unset.
+    ///
+    /// The socket will be inherited by child processes spawned after this
+    /// call. Prefer `connect` unless the descriptor is meant to be handed to
+    /// a child.
+    pub fn connect_inheritable<P: AsRef<Path>>(path: P) -> io::Result<UnixStream> {
+        let inner = try!(Inner::new_inheritable(libc::SOCK_STREAM));
+        try!(inner.connect(path));
+        Ok(UnixStream::from_inner(inner))
+    }
+
+    /// Connects to the socket at `addr`.
+    ///
+    /// Unlike `connect`, the address namespace is chosen explicitly by the
+    /// `Address` variant rather than by a leading null byte.
+    /// `Address::Unnamed` can't be connected to and returns an error of kind
+    /// `InvalidInput`.
+    pub fn connect_address<A: Into<Address>>(addr: A) -> io::Result<UnixStream> {
+        let inner = try!(Inner::new(libc::SOCK_STREAM));
+        try!(inner.connect_address(addr.into()));
+        Ok(UnixStream::from_inner(inner))
+    }
+
+    /// Connects to the socket at `addr`, using `len` as the address length.
+    ///
+    /// See `UnixListener::bind_addr_with_len` for why the length matters.
+    pub fn connect_addr_with_len(addr: &SocketAddr, len: usize) -> io::Result<UnixStream> {
+        let len = try!(addr.explicit_len(len));
+        let inner = try!(Inner::new(libc::SOCK_STREAM));
+        unsafe {
+            try!(cvt(libc::connect(inner.0, &addr.addr as *const _ as *const _, len)));
+        }
+        Ok(UnixStream::from_inner(inner))
+    }
+
+    /// Creates an unnamed pair of connected sockets.
+    ///
+    /// Returns two `UnixStream`s which are connected to each other. Both have
+    /// close-on-exec set.
+    pub fn pair() -> io::Result<(UnixStream, UnixStream)> {
+        let (i1, i2) = try!(Inner::new_pair(libc::SOCK_STREAM));
+        Ok((UnixStream::from_inner(i1), UnixStream::from_inner(i2)))
+    }
+
+    /// Creates an unnamed pair of connected sockets, applying `cfg` to both.
+    ///
+    /// Returns two `UnixStream`s which are connected to each other.
+    pub fn configured_pair(cfg: &SocketConfig) -> io::Result<(UnixStream, UnixStream)> {
+        let (s1, s2) = try!(UnixStream::pair());
+        try!(s1.inner.configure(cfg));
+        try!(s2.inner.configure(cfg));
+        Ok((s1, s2))
+    }
+
+    /// Adopts a file descriptor as a `UnixStream`, returning it along with
+    /// its address.
+    ///
+    /// The descriptor is validated to be a Unix stream socket. The returned
+    /// address is the peer's if the socket is connected, and the socket's
+    /// own address otherwise. This is convenient for descriptors received
+    /// from another process.
+    ///
+    /// # Safety
+    ///
+    /// On success, the returned `UnixStream` takes ownership of `fd`, which
+    /// must not be closed or used elsewhere. On error, ownership is not
+    /// taken.
+    pub unsafe fn from_fd_with_addr(fd: RawFd) -> io::Result<(UnixStream, SocketAddr)> {
+        let stream = UnixStream::from_raw_fd(fd);
+        match stream.adopted_addr() {
+            Ok(addr) => Ok((stream, addr)),
+            Err(e) => {
+                mem::forget(stream);
+                Err(e)
+            }
+        }
+    }
+
+    fn adopted_addr(&self) -> io::Result<SocketAddr> {
+        if try!(self.inner.socket_type()) != SocketType::Stream {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "file descriptor is not a stream socket"));
+        }
+        let local = try!(self.local_addr());
+        match self.peer_addr() {
+            Ok(addr) => Ok(addr),
+            Err(ref e) if e.raw_os_error() == Some(libc::ENOTCONN) => Ok(local),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates a new independently owned handle to the underlying socket.
+    ///
+    /// The returned `UnixStream` is a reference to the same stream that this
+    /// object references. Both handles will read and write the same stream of
+    /// data, and options set on one stream will be propogated to the other
+    /// stream.
+    pub fn try_clone(&self) -> io::Result<UnixStream> {
+        Ok(UnixStream {
+            inner: try!(self.inner.try_clone()),
+            id: self.id,
+            created: self.created,
+        })
+    }
+
+    /// Returns the time elapsed since this stream was created.
+    ///
+    /// This is measured from when the stream was connected, accepted or
+    /// wrapped with `from_raw_fd`, and handles made with `try_clone` share
+    /// the original's creation time. It uses the monotonic clock behind
+    /// `Instant`, so it is only meaningful within this process.
+    pub fn age(&self) -> Duration {
+        self.created.elapsed()
+    }
+
+    /// Duplicates the socket's descriptor onto `target` with `dup2`,
+    /// closing whatever `target` previously referred to.
+    ///
+    /// This places the socket at a fixed descriptor number, such as when
+    /// handing it to a child process which expects it there. The new
+    /// descriptor is independent of this stream, which remains open. Unlike
+    /// other descriptors created by this crate, `target` does not have
+    /// close-on-exec set.
+    ///
+    /// `target` must not be this stream's own descriptor.
+    ///
+    /// On success, returns `target`.
+    ///
+    /// # Safety
+    ///
+    /// Whatever `target` refers to is closed, so the caller must own it, or
+    /// it must not be open. On success, the caller owns the new descriptor
+    /// at `target` and is responsible for closing it.
+    pub unsafe fn dup_to(&self, target: RawFd) -> io::Result<RawFd> {
+        if target == self.inner.0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "target is the stream's own descriptor"));
+        }
+        cvt(libc::dup2(self.inner.0, target))
+    }
+
+    /// Returns an identifier for this connection, for correlating log
+    /// entries.
+    ///
+    /// Each stream created or accepted by this process is assigned a new
+    /// identifier, which handles made with `try_clone` share. Unlike the
+    /// file descriptor, an identifier is never reused by a later connection.
+    /// It is only meaningful within this process.
+    pub fn connection_id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the socket address of the local half of this connection.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        SocketAddr::new(|addr, len| unsafe { libc::getsockname(self.inner.0, addr, len) })
+    }
+
+    /// Returns the address of this socket, or `None` if it is not bound to
+    /// one.
+    ///
+    /// Unlike `local_addr`, an unnamed address and any error are both
+    /// reported as `None`, which is convenient when displaying the address.
+    pub fn local_addr_opt(&self) -> Option<SocketAddr> {
+        match self.local_addr() {
+            Ok(ref addr) if addr.is_unnamed() => None,
+            Ok(addr) => Some(addr),
+            Err(_) => None,
+        }
+    }
+
+    /// Returns the socket address of the remote half of this connection.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        SocketAddr::new(|addr, len| unsafe { libc::getpeername(self.inner.0, addr, len) })
+    }
+
+    /// Returns `true` if neither end of this connection is bound to an
+    /// address.
+    ///
+    /// This is the case for sockets created by `pair`, and is intended as a
+    /// diagnostic aid. If either address can't be retrieved, `false` is
+    /// returned.
+    pub fn is_pair_end(&self) -> bool {
+        match (self.local_addr(), self.peer_addr()) {
+            (Ok(local), Ok(peer)) => local.is_unnamed() && peer.is_unnamed(),
+            _ => false,
+        }
+    }
+
+    /// Sets the read timeout for the socket.
+    ///
+    /// If the provided value is `None`, then `read` calls will block
+    /// indefinitely. It is an error to pass the zero `Duration` to this
+    /// method.
+    ///
+    /// A receive that times out fails with an error of kind `TimedOut`.
+    /// The `EAGAIN` reported by the operating system is translated so that
+    /// timeouts can be told apart from nonblocking receives, which fail with
+    /// `WouldBlock`.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeout(timeout, libc::SO_RCVTIMEO)
+    }
+
+    /// Sets the read timeout for the socket, treating a zero `Duration` as a
+    /// request for nonblocking mode.
+    ///
+    /// Passing `Some` of the zero `Duration` clears the read timeout and puts
+    /// the socket into nonblocking mode, so reads fail with `WouldBlock`
+    /// rather than waiting. Any other value sets the read timeout as
+    /// `set_read_timeout` does and puts the socket into blocking mode. Note
+    /// that nonblocking mode applies to writes as well as reads.
+    pub fn set_read_timeout_or_nonblocking(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_read_timeout_or_nonblocking(timeout)
+    }
+
+    /// Sets the write timeout for the socket.
+    ///
+    /// If the provided value is `None`, then `write` calls will block
+    /// indefinitely. It is an error to pass the zero `Duration` to this
+    /// method.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeout(timeout, libc::SO_SNDTIMEO)
+    }
+
+    /// Returns the read timeout of this socket.
+    ///
+    /// This is the value stored by the kernel rather than the one passed to
+    /// `set_read_timeout`. Timeouts are stored with at most microsecond
+    /// resolution, so any finer part is truncated, and Linux stores them as
+    /// a whole number of clock ticks, so short timeouts may be rounded up.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.inner.timeout(libc::SO_RCVTIMEO)
+    }
+
+    /// Returns the write timeout of this socket.
+    ///
+    /// This is the value stored by the kernel rather than the one passed to
+    /// `set_write_timeout`. Timeouts are stored with at most microsecond
+    /// resolution, so any finer part is truncated, and Linux stores them as
+    /// a whole number of clock ticks, so short timeouts may be rounded up.
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        self.inner.timeout(libc::SO_SNDTIMEO)
+    }
+
+    /// Sets the read and write timeouts for the socket together.
+    ///
+    /// The values are interpreted as by `set_read_timeout` and
+    /// `set_write_timeout`. If the write timeout can't be set, the read
+    /// timeout is restored to its previous value, so the socket is never
+    /// left with only one of them changed.
+    pub fn set_timeouts(&self, read: Option<Duration>, write: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeouts(read, write)
+    }
+
+    /// Returns the read and write timeouts of this socket.
+    pub fn timeouts(&self) -> io::Result<(Option<Duration>, Option<Duration>)> {
+        let read = try!(self.inner.timeout(libc::SO_RCVTIMEO));
+        let write = try!(self.inner.timeout(libc::SO_SNDTIMEO));
+        Ok((read, write))
+    }
+
+    /// Sets the size of the socket's send buffer.
+    ///
+    /// The kernel may adjust the requested size; on Linux it is doubled to
+    /// leave room for bookkeeping overhead and capped by the system
+    /// `wmem_max` limit.
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.inner.set_buffer_size(size, libc::SO_SNDBUF)
+    }
+
+    /// Returns the size of the socket's send buffer.
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.inner.buffer_size(libc::SO_SNDBUF)
+    }
+
+    /// Sets the size of the socket's receive buffer.
+    ///
+    /// The kernel may adjust the requested size; on Linux it is doubled to
+    /// leave room for bookkeeping overhead and capped by the system
+    /// `rmem_max` limit.
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.inner.set_buffer_size(size, libc::SO_RCVBUF)
+    }
+
+    /// Returns the size of the socket's receive buffer.
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        self.inner.buffer_size(libc::SO_RCVBUF)
+    }
+
+    /// Returns the size of the socket's receive buffer along with the system
+    /// limit on the size which may be requested.
+    ///
+    /// This shows whether a requested size was clamped. On Linux the limit
+    /// is `net.core.rmem_max`, and because the kernel doubles requested
+    /// sizes the buffer may be up to twice the limit. Other platforms return
+    /// an error.
+    pub fn recv_buffer_limits(&self) -> io::Result<(usize, usize)> {
+        self.inner.recv_buffer_limits()
+    }
+
+    /// Sets the value of the `SO_KEEPALIVE` option on this socket.
+    ///
+    /// Unix domain sockets have no keepalive probes, so this typically has
+    /// no effect. It is accepted for uniformity with code that configures
+    /// TCP sockets the same way.
+    pub fn set_keepalive(&self, keepalive: bool) -> io::Result<()> {
+        self.inner.setsockopt(libc::SOL_SOCKET, libc::SO_KEEPALIVE, keepalive as libc::c_int)
+    }
+
+    /// Returns the value of the `SO_KEEPALIVE` option on this socket.
+    pub fn keepalive(&self) -> io::Result<bool> {
+        self.inner.getsockopt::<libc::c_int>(libc::SOL_SOCKET, libc::SO_KEEPALIVE).map(|v| v != 0)
+    }
+
+    /// Moves the socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.inner.set_nonblocking(nonblocking)
+    }
+
+    /// Returns whether the socket is in nonblocking mode.
+    ///
+    /// This reads the descriptor's `O_NONBLOCK` flag, so it reflects the
+    /// mode however it was set, including by a constructor.
+    pub fn nonblocking(&self) -> io::Result<bool> {
+        self.inner.nonblocking()
+    }
+
+    /// Moves the socket into or out of nonblocking mode, returning whether
+    /// it was previously in nonblocking mode.
+    ///
+    /// The mode is only changed if it differs from the requested one.
+    pub fn swap_nonblocking(&self, nonblocking: bool) -> io::Result<bool> {
+        self.inner.swap_nonblocking(nonblocking)
+    }
+
+    /// Applies the options set in `cfg` to the socket.
+    ///
+    /// The options are applied in the order of `SocketConfig`'s fields. If
+    /// one can't be set, the error identifies it, and the options before it
+    /// are restored to their previous values on a best-effort basis.
+    pub fn configure(&self, cfg: &SocketConfig) -> Result<(), ConfigError> {
+        self.inner.configure(cfg)
+    }
+
+    /// Returns the value of the `SO_ERROR` option.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.inner.take_error()
+    }
+
+    /// Returns the file descriptor flags of the socket, as reported by
+    /// `fcntl(F_GETFD)`.
+    ///
+    /// This is useful for tracking down descriptor leaks, since a socket
+    /// without `FD_CLOEXEC` set will be inherited by child processes.
+    pub fn fd_flags(&self) -> io::Result<i32> {
+        self.inner.fd_flags()
+    }
+
+    /// Returns the type of the socket, as reported by `SO_TYPE`.
+    ///
+    /// This is useful for checking a socket created with `from_raw_fd`.
+    pub fn socket_type(&self) -> io::Result<SocketType> {
+        self.inner.socket_type()
+    }
+
+    /// Returns whether this handle and `other` refer to the same socket.
+    ///
+    /// Handles made with `try_clone`, or received from another process
+    /// which passed this socket, refer to the same socket, while separate
+    /// connections never do. On Linux this compares the open files with
+    /// `kcmp`; where that is unavailable, and on other platforms, the
+    /// sockets' inode numbers are compared instead.
+    pub fn same_socket(&self, other: &UnixStream) -> io::Result<bool> {
+        self.inner.same_socket(&other.inner)
+    }
+
+    /// Waits for the socket to become readable or writable.
+    ///
+    /// If `timeout` is `None`, this blocks until the socket is ready.
+    /// Otherwise it waits at most `timeout`, and the returned `Ready` will
+    /// report neither readable nor writable if it elapsed first.
+    pub fn poll_ready(&self, timeout: Option<Duration>) -> io::Result<Ready> {
+        self.inner.poll(libc::POLLIN | libc::POLLOUT, timeout)
+    }
+
+    /// Returns `true` if a read from the socket would not block.
+    pub fn readable(&self) -> io::Result<bool> {
+        self.inner.poll(libc::POLLIN, Some(Duration::from_secs(0))).map(|r| r.is_readable())
+    }
+
+    /// Returns `true` if a write to the socket would not block.
+    pub fn writable(&self) -> io::Result<bool> {
+        self.inner.poll(libc::POLLOUT, Some(Duration::from_secs(0))).map(|r| r.is_writable())
+    }
+
+    /// Closes the socket, returning any error reported by `close`.
+    ///
+    /// Dropping the socket also closes it but silently ignores errors. The
+    /// descriptor is released even if an error is returned.
+    ///
+    /// # Examples
+    ///
+    /// The socket is consumed, so it can't be used after being closed:
+    ///
+    /// ```rust,compile_fail
+    /// use std::os::unix::io::AsRawFd;
+    /// use unix_socket::UnixStream;
+    ///
+    /// let (stream, _) = UnixStream::pair().unwrap();
+    /// stream.close().unwrap();
+    /// stream.as_raw_fd();
+    /// ```
+    pub fn close(self) -> io::Result<()> {
+        self.inner.close()
+    }
+
+    /// Closes the connection abruptly, discarding any queued data.
+    ///
+    /// `SO_LINGER` is set with a zero timeout before closing, the Unix
+    /// socket equivalent of a TCP reset. Whether the peer sees a reset
+    /// rather than a clean end of file depends on the platform: Linux
+    /// ignores `SO_LINGER` on Unix sockets, and reports `ECONNRESET` to the
+    /// peer only if data it sent was still unread when the socket was
+    /// closed.
+    ///
+    /// The descriptor is released even if an error is returned.
+    pub fn reset(self) -> io::Result<()> {
+        let linger = libc::linger {
+            l_onoff: 1,
+            l_linger: 0,
+        };
+        let res = self.inner.setsockopt(libc::SOL_SOCKET, libc::SO_LINGER, linger);
+        let close = self.inner.close();
+        res.and(close)
+    }
+
+    /// Closes the connection gracefully, taking no longer than `timeout`.
+    ///
+    /// This waits for the peer to consume the data queued for it, shuts
+    /// down the write half, then reads and discards anything the peer sends
+    /// until it closes its end, and finally closes the socket. All of the
+    /// steps share the one timeout. If they don't complete in time an error
+    /// of kind `TimedOut` is returned.
+    ///
+    /// The descriptor is released even if an error is returned.
+    pub fn graceful_close(self, timeout: Duration) -> io::Result<()> {
+        let deadline = Instant::now() + timeout;
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if try!(self.inner.wait_send_queue(Some(remaining))) != 0 {
+            return Err(io::Error::from_raw_os_error(libc::ETIMEDOUT));
+        }
+        try!(self.inner.shutdown(Shutdown::Write));
+        let mut buf = [0; 512];
+        while try!(self.inner.recv_deadline(&mut buf, deadline)) != 0 {}
+        self.inner.close()
+    }
+
+    /// Receives data from the socket, blocking until `buf` is full.
+    ///
+    /// This uses the `MSG_WAITALL` flag, so the buffer is filled by a single
+    /// call rather than a read loop. Fewer bytes are returned only if the
+    /// peer shuts down its write half (or the call is interrupted by a
+    /// signal). If a read timeout is set and expires after some data has
+    /// arrived, the data received so far is returned; if nothing has arrived
+    /// the call fails with a `TimedOut` error.
+    pub fn recv_exact(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.recv_with_flags(buf, libc::MSG_WAITALL)
+    }
+
+    /// Receives data from the socket, waiting no later than `deadline`.
+    ///
+    /// This is convenient when several operations share an overall
+    /// deadline, since the remaining time doesn't need to be recomputed for
+    /// each one. If no data arrives before `deadline`, or it has already
+    /// passed, an error of kind `TimedOut` is returned. The socket's read
+    /// timeout is not used.
+    ///
+    /// On success, returns the number of bytes read.
+    pub fn recv_deadline(&self, buf: &mut [u8], deadline: Instant) -> io::Result<usize> {
+        self.inner.recv_deadline(buf, deadline)
+    }
+
+    /// Reads all bytes until EOF, appending them to `out`, unless there are
+    /// more than `max` of them.
+    ///
+    /// This is like `Read::read_to_end`, but bounds the memory an untrusted
+    /// peer can make this process allocate. If the peer sends more than
+    /// `max` bytes, an error of kind `InvalidData` is returned, and `out`
+    /// holds the first `max` bytes.
+    ///
+    /// On success, returns the number of bytes appended to `out`.
+    pub fn read_to_end_capped(&self, max: usize, out: &mut Vec<u8>) -> io::Result<usize> {
+        let mut buf = [0; 8192];
+        let mut total = 0;
+        loop {
+            // Ask for one byte beyond the limit to detect when it is exceeded
+            let len = cmp::min(buf.len(), (max - total).saturating_add(1));
+            let count = match self.inner.recv(&mut buf[..len]) {
+                Ok(0) => return Ok(total),
+                Ok(count) => count,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if count > max - total {
+                out.extend_from_slice(&buf[..max - total]);
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          "data exceeds the size limit"));
+            }
+            out.extend_from_slice(&buf[..count]);
+            total += count;
+        }
+    }
+
+    /// Receives data from the socket until `buf` is full, `timeout` elapses
+    /// or the peer shuts down its write half.
+    ///
+    /// Unlike `recv_exact`, a short read is not an error: once the timeout
+    /// elapses the data received so far is returned, which may be none at
+    /// all. The socket's read timeout is not used.
+    ///
+    /// On success, returns the number of bytes read.
+    pub fn recv_timed_partial(&self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        let deadline = Instant::now() + timeout;
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.inner.recv_deadline(&mut buf[filled..], deadline) {
+                Ok(0) => break,
+                Ok(count) => filled += count,
+                Err(ref e) if e.raw_os_error() == Some(libc::ETIMEDOUT) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(filled)
+    }
+
+    /// Receives data from the socket into a possibly uninitialized buffer.
+    ///
+    /// This avoids zeroing a large receive buffer before each call. On
+    /// success, returns the number of bytes read; only that many bytes at
+    /// the start of `buf` have been initialized.
+    ///
+    /// This is the stable counterpart of a `read_buf` taking an
+    /// `io::BorrowedCursor`. `BorrowedCursor` and `io::Read::read_buf` are
+    /// still unstable, so neither is provided; the buffer behind a cursor
+    /// can be passed here and the cursor advanced by the count returned.
+    pub fn recv_buf(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+        self.inner.recv_uninit(buf, 0)
+    }
+
+    /// Receives data from the socket into a series of buffers.
+    ///
+    /// The buffers are filled in order by a single `recvmsg` call.
+    ///
+    /// On success, returns the total number of bytes read.
+    pub fn recv_vectored(&self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        self.inner.recv_msg(bufs, &mut [], 0).map(|msg| msg.count)
+    }
+
+    /// Receives data from the socket into a series of buffers, returning the
+    /// number of bytes written to each.
+    ///
+    /// The buffers are filled in order, so every buffer before the last one
+    /// written to is full. The returned counts are in the same order as
+    /// `bufs`.
+    pub fn read_vectored_filled(&self, bufs: &mut [IoSliceMut]) -> io::Result<Vec<usize>> {
+        let mut remaining = try!(self.recv_vectored(bufs));
+        Ok(bufs.iter()
+               .map(|buf| {
+                   let filled = cmp::min(buf.len(), remaining);
+                   remaining -= filled;
+                   filled
+               })
+               .collect())
+    }
+
+    /// Receives data from the socket into two buffers, filling `first`
+    /// before `second`.
+    ///
+    /// This is intended for ring buffers, whose free space is split in two
+    /// where it wraps around, allowing it to be filled by a single call
+    /// without an intermediate copy.
+    ///
+    /// On success, returns the total number of bytes read.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use unix_socket::UnixStream;
+    ///
+    /// let stream = UnixStream::connect("/path/to/my/socket").unwrap();
+    /// let mut ring = [0; 4096];
+    /// // The data occupies 1024..3072, so the free space is 3072..4096 followed
+    /// // by 0..1024
+    /// let (head, tail) = ring.split_at_mut(3072);
+    /// let count = stream.recv_into_two(tail, &mut head[..1024]).unwrap();
+    /// println!("received {} bytes", count);
+    /// ```
+    pub fn recv_into_two(&self, first: &mut [u8], second: &mut [u8]) -> io::Result<usize> {
+        self.recv_vectored(&mut [IoSliceMut::new(first), IoSliceMut::new(second)])
+    }
+
+    /// Reads bytes from the socket into `out` until the delimiter `delim` or
+    /// EOF is reached.
+    ///
+    /// This behaves like `BufRead::read_until`, appending everything read up
+    /// to and including the delimiter, but doesn't need a `BufReader`.
+    /// Incoming data is inspected with `MSG_PEEK` so that nothing past the
+    /// delimiter is consumed from the socket.
+    ///
+    /// On success, returns the number of bytes appended to `out`.
+    pub fn read_until(&self, delim: u8, out: &mut Vec<u8>) -> io::Result<usize> {
+        let mut buf = [0; 512];
+        let mut total = 0;
+        loop {
+            let peeked = match self.inner.recv_with_flags(&mut buf, libc::MSG_PEEK) {
+                Ok(0) => return Ok(total),
+                Ok(n) => n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            let (len, done) = match buf[..peeked].iter().position(|&b| b == delim) {
+                Some(pos) => (pos + 1, true),
+                None => (peeked, false),
+            };
+            // The peeked bytes are still queued, so this won't block
+            let count = try!(self.inner.recv(&mut buf[..len]));
+            out.extend_from_slice(&buf[..count]);
+            total += count;
+            if done && count == len {
+                return Ok(total);
+            }
+        }
+    }
+
+    /// Sends data on the socket along with file descriptors.
+    ///
+    /// The descriptors are passed to the peer with `SCM_RIGHTS` and remain
+    /// open in this process. At least one byte of data must be sent for the
+    /// descriptors to be delivered.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_fds(&self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        self.inner.send_fds(buf, fds)
+    }
+
+    /// Attempts to send data on the socket without blocking, for use in
+    /// hand-written futures.
+    ///
+    /// The send is made with `MSG_DONTWAIT`. If it would block,
+    /// `Poll::Pending` is returned; no wakeup is registered, so the caller's
+    /// reactor must arrange to poll again once the socket is writable.
+    /// Otherwise the result of the send is returned as `Poll::Ready`.
+    pub fn try_send_poll(&self, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.inner.try_send_poll(buf)
+    }
+
+    /// Sends a batch of file descriptors to the peer in a single message.
+    ///
+    /// All of the descriptors are passed in one `SCM_RIGHTS` control message
+    /// accompanied by a single byte of data, which is cheaper than passing
+    /// them one at a time. The receiver gets them all from one `recv_fds`.
+    /// Linux limits a single message to 253 descriptors.
+    pub fn send_fd_batch(&self, fds: &[RawFd]) -> io::Result<()> {
+        self.inner.send_fds(&[0], fds).map(|_| ())
+    }
+
+    /// Receives data from the socket along with any file descriptors passed
+    /// by the peer.
+    ///
+    /// The received descriptors are owned by the caller and have
+    /// close-on-exec set.
+    ///
+    /// On success, returns the number of bytes read and the descriptors.
+    pub fn recv_fds(&self, buf: &mut [u8]) -> io::Result<(usize, Vec<RawFd>)> {
+        let (msg, fds) = try!(self.inner.recv_fds(&mut [IoSliceMut::new(buf)]));
+        Ok((msg.count, fds))
+    }
+
+    /// Receives a Unix stream socket passed by the peer.
+    ///
+    /// This reads a single byte of data along with the descriptor, as sent
+    /// by `send_fd_batch` or `send_fds`. The descriptor must be the only one
+    /// in the message and must refer to an `AF_UNIX` stream socket, otherwise
+    /// an error of kind `InvalidData` is returned and any descriptors
+    /// received are closed.
+    ///
+    /// Returns `None` if no descriptor was attached to the data.
+    pub fn recv_stream(&self) -> io::Result<Option<UnixStream>> {
+        let mut buf = [0; 1];
+        let (_, fds) = try!(self.inner.recv_fds(&mut [IoSliceMut::new(&mut buf)]));
+        // Wrap the descriptors right away so they're closed on error
+        let mut fds = fds.into_iter().map(Inner).collect::<Vec<_>>();
+        let inner = match fds.pop() {
+            Some(inner) => inner,
+            None => return Ok(None),
+        };
+        if !fds.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      "expected a single file descriptor"));
+        }
+        if try!(domain(inner.0)) != libc::AF_UNIX ||
+           try!(inner.socket_type()) != SocketType::Stream {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      "file descriptor is not a Unix stream socket"));
+        }
+        Ok(Some(UnixStream::from_inner(inner)))
+    }
+
+    /// Shuts down the read, write, or both halves of this connection.
+    ///
+    /// This function will cause all pending and future I/O calls on the
+    /// specified portions to immediately return with an appropriate value
+    /// (see the documentation of `Shutdown`).
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.inner.shutdown(how)
+    }
+
+    /// Waits for the socket's send queue to drain.
+    ///
+    /// `Write::flush` is a no-op for Unix sockets, since data is handed to the
+    /// kernel as soon as it is written. This instead waits until the peer has
+    /// consumed everything queued, or until `timeout` elapses if one is given,
+    /// and returns the amount still queued. On Linux the amount is the
+    /// kernel's accounting of the queued buffers, which includes some
+    /// overhead beyond the bytes written.
+    ///
+    /// This is best-effort: on platforms other than Linux the send queue
+    /// cannot be inspected, so this returns `0` immediately.
+    pub fn drain(&self, timeout: Option<Duration>) -> io::Result<usize> {
+        self.inner.wait_send_queue(timeout)
+    }
+
+    /// Converts this stream into a handle which can be cloned and shared
+    /// between threads.
+    ///
+    /// Unlike `try_clone`, which duplicates the file descriptor, the clones
+    /// all share the one descriptor, which is closed once the last of them is
+    /// dropped.
+    pub fn into_shared(self) -> SharedUnixStream {
+        SharedUnixStream(Arc::new(self))
+    }
+
+    /// Shuts down the read, write, or both halves of this connection once
+    /// all outgoing data has been consumed by the peer.
+    ///
+    /// If `how` includes the write half, this waits for the socket's send
+    /// queue to drain before issuing the shutdown. This is best-effort: the
+    /// wait is bounded to a few seconds, after which the shutdown proceeds
+    /// regardless, and on platforms other than Linux the send queue cannot be
+    /// inspected so the shutdown happens immediately.
+    pub fn shutdown_after_flush(&self, how: Shutdown) -> io::Result<()> {
+        if how != Shutdown::Read {
+            try!(self.inner.wait_send_queue(Some(Duration::from_secs(FLUSH_TIMEOUT_SECS))));
+        }
+        self.inner.shutdown(how)
+    }
+}
+
+impl io::Read for UnixStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::Read::read(&mut &*self, buf)
+    }
+}
+
+impl<'a> io::Read for &'a UnixStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.recv(buf)
+    }
+}
+
+impl io::Write for UnixStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::Write::write(&mut &*self, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::Write::flush(&mut &*self)
+    }
+}
+
+impl<'a> io::Write for &'a UnixStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.send(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsRawFd for UnixStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.0
+    }
+}
+
+impl FromRawFd for UnixStream {
+    unsafe fn from_raw_fd(fd: RawFd) -> UnixStream {
+        UnixStream::from_inner(Inner(fd))
+    }
+}
+
+impl IntoRawFd for UnixStream {
+    fn into_raw_fd(self) -> RawFd {
+        let fd = self.inner.0;
+        mem::forget(self);
+        fd
+    }
+}
+
+/// A reference-counted handle to a `UnixStream`, created by
+/// `UnixStream::into_shared`.
+///
+/// Clones of the handle refer to the same socket and may be used to read
+/// and write concurrently from several threads. The kernel serializes the
+/// individual calls, but no ordering is imposed between the handles.
+#[derive(Clone, Debug)]
+pub struct SharedUnixStream(Arc<UnixStream>);
+
+impl SharedUnixStream {
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &UnixStream {
+        &self.0
+    }
+}
+
+impl io::Read for SharedUnixStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::Read::read(&mut &*self.0, buf)
+    }
+}
+
+impl<'a> io::Read for &'a SharedUnixStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::Read::read(&mut &*self.0, buf)
+    }
+}
+
+impl io::Write for SharedUnixStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::Write::write(&mut &*self.0, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> io::Write for &'a SharedUnixStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::Write::write(&mut &*self.0, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsRawFd for SharedUnixStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+// The default capacity of a BufferedUnixStream's read buffer.
+const DEFAULT_READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// A `UnixStream` with a buffer for reads.
+///
+/// This is similar to wrapping the stream in a `BufReader`, but the stream
+/// stays reachable through `get_ref` so that its other methods may still be
+/// used. Reads smaller than the buffer are served from it, refilling it
+/// with a single call when it is empty. Writes are passed straight through.
+pub struct BufferedUnixStream {
+    stream: UnixStream,
+    buf: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+}
+
+impl fmt::Debug for BufferedUnixStream {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("BufferedUnixStream")
+           .field("stream", &self.stream)
+           .field("buffered", &(self.filled - self.pos))
+           .field("capacity", &self.buf.len())
+           .finish()
+    }
+}
+
+impl BufferedUnixStream {
+    /// Creates a new `BufferedUnixStream` with a buffer of 8 KiB.
+    pub fn new(stream: UnixStream) -> BufferedUnixStream {
+        BufferedUnixStream::with_capacity(DEFAULT_READ_BUFFER_SIZE, stream)
+    }
+
+    /// Creates a new `BufferedUnixStream` with a buffer of `capacity`
+    /// bytes.
+    pub fn with_capacity(capacity: usize, stream: UnixStream) -> BufferedUnixStream {
+        BufferedUnixStream {
+            stream: stream,
+            buf: vec![0; capacity].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// Returns a reference to the underlying stream.
+    ///
+    /// Reading from the stream directly skips over any data in the buffer.
+    pub fn get_ref(&self) -> &UnixStream {
+        &self.stream
+    }
+
+    /// Returns the data currently in the buffer.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+
+    /// Returns the capacity of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the underlying stream.
+    ///
+    /// Any data in the buffer is lost.
+    pub fn into_inner(self) -> UnixStream {
+        self.stream
+    }
+}
+
+impl io::Read for BufferedUnixStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Large reads bypass an empty buffer rather than copying through it
+        if self.pos == self.filled && buf.len() >= self.buf.len() {
+            return io::Read::read(&mut self.stream, buf);
+        }
+        let count = {
+            let available = try!(io::BufRead::fill_buf(self));
+            let count = cmp::min(available.len(), buf.len());
+            buf[..count].copy_from_slice(&available[..count]);
+            count
+        };
+        io::BufRead::consume(self, count);
+        Ok(count)
+    }
+}
+
+impl io::BufRead for BufferedUnixStream {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.filled {
+            self.filled = try!(io::Read::read(&mut self.stream, &mut self.buf));
+            self.pos = 0;
+        }
+        Ok(&self.buf[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = cmp::min(self.pos + amt, self.filled);
+    }
+}
+
+impl io::Write for BufferedUnixStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::Write::write(&mut self.stream, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsRawFd for BufferedUnixStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.stream.as_raw_fd()
+    }
+}
+
+/// Reusable storage for the `iovec` array passed to `sendmsg`, for sending
+/// gathered data repeatedly without allocating.
+///
+/// The array grows to fit the largest number of buffers sent through it and
+/// is then reused.
+#[derive(Debug, Default)]
+pub struct VectoredSender {
+    iovecs: Vec<libc::iovec>,
+}
+
+// The iovecs are cleared after each send, so no pointers are held between
+// calls.
+unsafe impl Send for VectoredSender {}
+unsafe impl Sync for VectoredSender {}
+
+impl VectoredSender {
+    /// Creates a new `VectoredSender`.
+    pub fn new() -> VectoredSender {
+        VectoredSender::default()
+    }
+
+    /// Creates a new `VectoredSender` with room for `capacity` buffers.
+    pub fn with_capacity(capacity: usize) -> VectoredSender {
+        VectoredSender { iovecs: Vec::with_capacity(capacity) }
+    }
+
+    /// Sends the data in `bufs` on `sock` with a single `sendmsg` call.
+    ///
+    /// As with `Write::write_vectored`, not all of the data may be written.
+    ///
+    /// On success, returns the total number of bytes written.
+    pub fn send_vectored(&mut self, sock: &UnixStream, bufs: &[IoSlice]) -> io::Result<usize> {
+        self.iovecs.extend(bufs.iter().map(|buf| {
+            libc::iovec {
+                iov_base: buf.as_ptr() as *mut _,
+                iov_len: buf.len(),
+            }
+        }));
+        let res = sock.inner.send_iovecs(&mut self.iovecs);
+        self.iovecs.clear();
+        res
+    }
+}
+
+
+/// A structure representing a Unix domain seqpacket socket server.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::thread;
+/// use unix_socket::{UnixSeqpacket, UnixSeqpacketListener};
+///
+/// fn handle_client(_stream: UnixSeqpacket) {
+///     // ...
+/// }
+///
+/// let listener = UnixSeqpacketListener::bind("/path/to/the/socket").unwrap();
+///
+/// // accept connections and process them, spawning a new thread for each one
+/// for sock in listener.incoming() {
+///     match sock {
+///         Ok(sock) => {
+///             /* connection succeeded */
+///             thread::spawn(|| handle_client(sock));
+///         }
+///         Err(_err) => {
+///             /* connection failed */
+///             break;
+///         }
+///     }
+/// }
+///
+/// // close the listener socket
+/// drop(listener);
+/// ```
+pub struct UnixSeqpacketListener {
+    inner: Inner,
+    unlink: UnlinkGuard,
+}
+
+impl fmt::Debug for UnixSeqpacketListener {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut builder = fmt.debug_struct("UnixSeqpacketListener");
+        builder.field("fd", &self.inner.0);
+        if let Ok(flags) = self.inner.fd_flags() {
+            builder.field("cloexec", &(flags & libc::FD_CLOEXEC != 0));
+        }
+        if let Ok(accepting) = self.inner.accepting() {
+            builder.field("accepting", &accepting);
+        }
+        if let Ok(addr) = self.local_addr() {
+            builder.field("local", &addr);
+        }
+        if let Ok((pending, _)) = self.inner.listen_queues() {
+            builder.field("pending", &pending);
+        }
+        builder.finish()
+    }
+}
+
+impl UnixSeqpacketListener {
+    /// Creates a new `UnixSeqpacketListener` bound to the specified socket.
+    ///
+    /// Linux provides, as a nonportable extension, a separate "abstract"
+    /// address namespace as opposed to filesystem-based addressing. If `path`
+    /// begins with a null byte, it will be interpreted as an "abstract"
+    /// address. Otherwise, it will be interpreted as a "pathname" address,
+    /// corresponding to a path on the filesystem.
+    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<UnixSeqpacketListener> {
+        unsafe {
+            let inner = try!(Inner::new(libc::SOCK_SEQPACKET));
+            let (addr, len) = try!(sockaddr_un(path));
+
+            try!(cvt(libc::bind(inner.0, &addr as *const _ as *const _, len)));
+            try!(cvt(libc::listen(inner.0, LISTEN_BACKLOG)));
+
+            Ok(UnixSeqpacketListener {
+                inner: inner,
+                unlink: UnlinkGuard(None),
+            })
+        }
+    }
+
+    /// Creates a new `UnixSeqpacketListener` bound to `addr`.
+    ///
+    /// Unlike `bind`, the address namespace is chosen explicitly by the
+    /// `Address` variant rather than by a leading null byte.
+    pub fn bind_address<A: Into<Address>>(addr: A) -> io::Result<UnixSeqpacketListener> {
+        let (addr, len) = try!(addr.into().sockaddr());
+        unsafe {
+            let inner = try!(Inner::new(libc::SOCK_SEQPACKET));
+
+            try!(cvt(libc::bind(inner.0, &addr as *const _ as *const _, len)));
+            try!(cvt(libc::listen(inner.0, LISTEN_BACKLOG)));
+
+            Ok(UnixSeqpacketListener {
+                inner: inner,
+                unlink: UnlinkGuard(None),
+            })
+        }
+    }
+
+    /// Accepts a new incoming connection to this listener.
+    ///
+    /// This function will block the calling thread until a new Unix connection
+    /// is established. When established, the corersponding `UnixSeqpacket` and
+    /// the remote peer's address will be returned.
+    ///
+    /// The returned socket is in blocking mode, even if the listener is
+    /// nonblocking, and has close-on-exec set.
+    pub fn accept(&self) -> io::Result<(UnixSeqpacket, SocketAddr)> {
+        unsafe {
+            let mut fd = 0;
+            let addr = try!(SocketAddr::new(|addr, len| {
+                fd = accept_cloexec(self.inner.0, addr, len);
+                fd
+            }));
+
+            let socket = UnixSeqpacket { inner: Inner(fd) };
+            // Some platforms copy the listener's O_NONBLOCK flag
+            #[cfg(not(target_os = "linux"))]
+            try!(socket.inner.set_nonblocking(false));
+            Ok((socket, addr))
+        }
+    }
+
+    /// Creates a new independently owned handle to the underlying socket.
+    ///
+    /// The returned `UnixSeqpacketListener` is a reference to the same socket that this
+    /// object references. Both handles can be used to accept incoming
+    /// connections and options set on one listener will affect the other.
+    ///
+    /// The clone doesn't remove the socket file when dropped, even if
+    /// `set_unlink_on_drop` was enabled on this listener.
+    pub fn try_clone(&self) -> io::Result<UnixSeqpacketListener> {
+        Ok(UnixSeqpacketListener {
+            inner: try!(self.inner.try_clone()),
+            unlink: UnlinkGuard(None),
+        })
+    }
+
+    /// Returns the local socket address of this listener.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        SocketAddr::new(|addr, len| unsafe { libc::getsockname(self.inner.0, addr, len) })
+    }
+
+    /// Sets whether the listener's socket file is removed from the filesystem
+    /// when the listener is dropped.
+    ///
+    /// This is disabled by default. Enabling it records the listener's
+    /// current pathname address, and fails if the listener is not bound to
+    /// one.
+    ///
+    /// Only this handle removes the file. Clones made with `try_clone` never
+    /// do, whether they were made before or after this is enabled, so
+    /// dropping a clone can't remove a file still in use by the original.
+    pub fn set_unlink_on_drop(&mut self, unlink: bool) -> io::Result<()> {
+        let addr = self.local_addr();
+        self.unlink.set(unlink, addr)
+    }
+
+    /// Cancels a previous request to remove the listener's socket file when
+    /// it is dropped.
+    ///
+    /// This is useful when ownership of the socket is passed elsewhere and
+    /// the file must be kept.
+    pub fn forget_unlink(&mut self) {
+        self.unlink.0 = None;
+    }
+
+    /// Moves the socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.inner.set_nonblocking(nonblocking)
+    }
+
+    /// Returns whether the socket is in nonblocking mode.
+    ///
+    /// This reads the descriptor's `O_NONBLOCK` flag, so it reflects the
+    /// mode however it was set, including by a constructor.
+    pub fn nonblocking(&self) -> io::Result<bool> {
+        self.inner.nonblocking()
+    }
+
+    /// Moves the socket into or out of nonblocking mode, returning whether
+    /// it was previously in nonblocking mode.
+    ///
+    /// The mode is only changed if it differs from the requested one.
+    pub fn swap_nonblocking(&self, nonblocking: bool) -> io::Result<bool> {
+        self.inner.swap_nonblocking(nonblocking)
+    }
+
+    /// Returns the value of the `SO_ERROR` option.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.inner.take_error()
+    }
+
+    /// Returns the file descriptor flags of the socket, as reported by
+    /// `fcntl(F_GETFD)`.
+    ///
+    /// This is useful for tracking down descriptor leaks, since a socket
+    /// without `FD_CLOEXEC` set will be inherited by child processes.
+    pub fn fd_flags(&self) -> io::Result<i32> {
+        self.inner.fd_flags()
+    }
+
+    /// Returns the type of the socket, as reported by `SO_TYPE`.
+    ///
+    /// This is useful for checking a socket created with `from_raw_fd`.
+    pub fn socket_type(&self) -> io::Result<SocketType> {
+        self.inner.socket_type()
+    }
+
+    /// Returns whether this handle and `other` refer to the same socket.
+    ///
+    /// Handles made with `try_clone`, or received from another process
+    /// which passed this socket, refer to the same socket, while separate
+    /// connections never do. On Linux this compares the open files with
+    /// `kcmp`; where that is unavailable, and on other platforms, the
+    /// sockets' inode numbers are compared instead.
+    pub fn same_socket(&self, other: &UnixSeqpacketListener) -> io::Result<bool> {
+        self.inner.same_socket(&other.inner)
+    }
+
+    /// Closes the socket, returning any error reported by `close`.
+    ///
+    /// Dropping the socket also closes it but silently ignores errors. The
+    /// descriptor is released even if an error is returned.
+    pub fn close(self) -> io::Result<()> {
+        self.inner.close()
+    }
+
+    /// Returns the maximum number of pending connections the kernel will
+    /// queue for this listener.
+    ///
+    /// This is the backlog passed to `listen`, as clamped by the kernel. It
+    /// is currently only available on Linux, where it is read through the
+    /// `sock_diag` netlink interface; other platforms return an error.
+    pub fn backlog(&self) -> io::Result<usize> {
+        self.inner.listen_queues().map(|(_, backlog)| backlog)
+    }
+
+    /// Returns an iterator over incoming connections.
+    ///
+    /// The iterator will never return `None` and will also not yield the
+    /// peer's `SocketAddr` structure.
+    pub fn incoming<'a>(&'a self) -> IncomingSeqpacket<'a> {
+        IncomingSeqpacket { listener: self }
+    }
+}
+
+impl AsRawFd for UnixSeqpacketListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.0
+    }
+}
+
+impl FromRawFd for UnixSeqpacketListener {
+    unsafe fn from_raw_fd(fd: RawFd) -> UnixSeqpacketListener {
+        UnixSeqpacketListener {
+            inner: Inner(fd),
+            unlink: UnlinkGuard(None),
+        }
+    }
+}
+
+impl IntoRawFd for UnixSeqpacketListener {
+    fn into_raw_fd(mut self) -> RawFd {
+        self.forget_unlink();
+        let fd = self.inner.0;
+        mem::forget(self);
+        fd
+    }
+}
+
+impl<'a> IntoIterator for &'a UnixSeqpacketListener {
+    type Item = io::Result<UnixSeqpacket>;
+    type IntoIter = IncomingSeqpacket<'a>;
+
+    fn into_iter(self) -> IncomingSeqpacket<'a> {
+        self.incoming()
+    }
+}
+
+/// An iterator over incoming connections to a `UnixSeqpacketListener`.
+///
+/// It will never return `None`.
+#[derive(Debug)]
+pub struct IncomingSeqpacket<'a> {
+    listener: &'a UnixSeqpacketListener,
+}
+
+impl<'a> Iterator for IncomingSeqpacket<'a> {
+    type Item = io::Result<UnixSeqpacket>;
+
+    fn next(&mut self) -> Option<io::Result<UnixSeqpacket>> {
+        Some(self.listener.accept().map(|s| s.0))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::max_value(), None)
+    }
+}
+
+
+
+/// A structure representing a Unix domain stream socket server.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::thread;
+/// use unix_socket::{UnixStream, UnixListener};
+///
+/// fn handle_client(_stream: UnixStream) {
+///     // ...
+/// }
+///
+/// let listener = UnixListener::bind("/path/to/the/socket").unwrap();
+///
+/// // accept connections and process them, spawning a new thread for each one
+/// for stream in listener.incoming() {
+///     match stream {
+///         Ok(stream) => {
+///             /* connection succeeded */
+///             thread::spawn(|| handle_client(stream));
+///         }
+///         Err(_err) => {
+///             /* connection failed */
+///             break;
+///         }
+///     }
+/// }
+///
+/// // close the listener socket
+/// drop(listener);
+/// ```
+pub struct UnixListener {
+    inner: Inner,
+    unlink: UnlinkGuard,
+    template: Option<SocketConfig>,
+    // The path the socket file was renamed to by `bind_atomic`, which the
+    // kernel doesn't know about.
+    renamed: Option<SocketAddr>,
+}
+
+impl fmt::Debug for UnixListener {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut builder = fmt.debug_struct("UnixListener");
+        builder.field("fd", &self.inner.0);
+        if let Ok(flags) = self.inner.fd_flags() {
+            builder.field("cloexec", &(flags & libc::FD_CLOEXEC != 0));
+        }
+        if let Ok(accepting) = self.inner.accepting() {
+            builder.field("accepting", &accepting);
+        }
+        if let Ok(addr) = self.local_addr() {
+            builder.field("local", &addr);
+        }
+        if let Ok((pending, _)) = self.inner.listen_queues() {
+            builder.field("pending", &pending);
+        }
+        builder.finish()
+    }
+}
+
+impl UnixListener {
+    /// Creates a new `UnixListener` bound to the specified socket.
+    ///
+    /// Linux provides, as a nonportable extension, a separate "abstract"
+    /// address namespace as opposed to filesystem-based addressing. If `path`
+    /// begins with a null byte, it will be interpreted as an "abstract"
+    /// address. Otherwise, it will be interpreted as a "pathname" address,
+    /// corresponding to a path on the filesystem.
+    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<UnixListener> {
+        UnixListener::bind_socket(try!(Inner::new(libc::SOCK_STREAM)), path)
+    }
+
+    /// Creates a new `UnixListener` bound to the specified socket, in
+    /// nonblocking mode.
+    ///
+    /// This is intended for event loops. On Linux the socket is created
+    /// nonblocking with `SOCK_NONBLOCK`, so it is never observable in
+    /// blocking mode.
+    pub fn bind_nonblocking<P: AsRef<Path>>(path: P) -> io::Result<UnixListener> {
+        UnixListener::bind_socket(try!(Inner::new_nonblocking(libc::SOCK_STREAM)), path)
+    }
+
+    /// Creates a new `UnixListener` bound to `name` within the current
+    /// user's runtime directory, which is removed when it is dropped.
+    ///
+    /// The runtime directory is `$XDG_RUNTIME_DIR` if it is set, falling
+    /// back to `/run/user/<uid>` if that exists and then to the system
+    /// temporary directory. Missing parent directories of the socket are
+    /// created with mode 0700. Unlink on drop is enabled as with
+    /// `set_unlink_on_drop`.
+    ///
+    /// The parent directories below the runtime directory may already
+    /// exist, for example when another user has created them under a shared
+    /// temporary directory. An error of kind `PermissionDenied` is returned
+    /// unless each of them is a directory owned by the current user and not
+    /// writable by anyone else.
+    pub fn bind_runtime<P: AsRef<Path>>(name: P) -> io::Result<UnixListener> {
+        let dir = match env::var_os("XDG_RUNTIME_DIR") {
+            Some(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => {
+                let dir = PathBuf::from(format!("/run/user/{}", unsafe { libc::getuid() }));
+                if dir.is_dir() { dir } else { env::temp_dir() }
+            }
+        };
+        UnixListener::bind_runtime_in(&dir, name.as_ref())
+    }
+
+    fn bind_runtime_in(dir: &Path, name: &Path) -> io::Result<UnixListener> {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            try!(fs::DirBuilder::new().recursive(true).mode(0o700).create(parent));
+
+            let mut checked = vec![];
+            match parent.strip_prefix(dir) {
+                Ok(relative) => {
+                    let mut current = dir.to_path_buf();
+                    for component in relative.components() {
+                        current.push(component);
+                        checked.push(current.clone());
+                    }
+                }
+                Err(_) => checked.push(parent.to_path_buf()),
+            }
+            let uid = unsafe { libc::getuid() };
+            for dir in checked {
+                let meta = try!(fs::symlink_metadata(&dir));
+                if !meta.is_dir() || meta.uid() != uid || meta.mode() & 0o022 != 0 {
+                    return Err(io::Error::new(io::ErrorKind::PermissionDenied,
+                                              "socket directory is not private to the \
+                                               current user"));
+                }
+            }
+        }
+
+        let mut listener = try!(UnixListener::bind(&path));
+        try!(listener.set_unlink_on_drop(true));
+        Ok(listener)
+    }
+
+    /// Creates a new `UnixListener` bound to `path`, atomically replacing
+    /// any socket file already there.
+    ///
+    /// The listener is bound to a temporary path in the same directory,
+    /// which is then renamed to `path`. Clients connecting to `path` reach
+    /// either the old socket or the new one, and never find the file
+    /// missing, which allows restarts without downtime. Connections already
+    /// made to the old socket are unaffected, and the old listener keeps
+    /// accepting any that reached it until it is dropped.
+    ///
+    /// Only a socket file is replaced. If something else exists at `path`,
+    /// an `AddrInUse` error is returned and it is left alone.
+    ///
+    /// `local_addr`, `file_ident` and `set_unlink_on_drop` act on `path`,
+    /// although other processes see the temporary path as this listener's
+    /// address. `path` is looked up again each time, so once another
+    /// listener has replaced this one there, they act on the replacement.
+    /// Don't enable `set_unlink_on_drop` on a listener that is expected to
+    /// be replaced this way, or dropping it will remove its successor's file.
+    pub fn bind_atomic<P: AsRef<Path>>(path: P) -> io::Result<UnixListener> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = path.as_ref();
+        if fs::symlink_metadata(path).is_ok() && !is_socket_file(path) {
+            return Err(io::Error::from_raw_os_error(libc::EADDRINUSE));
+        }
+        let (addr, len) = try!(unsafe { sockaddr_un(path) });
+        let name = match path.file_name() {
+            Some(name) => name,
+            None => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "path does not name a file"))
+            }
+        };
+        let mut tmp_name = name.to_os_string();
+        tmp_name.push(format!(".tmp-{}-{}",
+                              unsafe { libc::getpid() },
+                              COUNTER.fetch_add(1, atomic::Ordering::Relaxed)));
+        let tmp_path = path.with_file_name(tmp_name);
+
+        let mut listener = try!(UnixListener::bind(&tmp_path));
+        if let Err(e) = fs::rename(&tmp_path, path) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+        listener.renamed = Some(SocketAddr {
+            addr: addr,
+            len: len,
+        });
+        Ok(listener)
+    }
+
+    fn bind_socket<P: AsRef<Path>>(inner: Inner, path: P) -> io::Result<UnixListener> {
+        unsafe {
+            let (addr, len) = try!(sockaddr_un(path));
+
+            try!(cvt(libc::bind(inner.0, &addr as *const _ as *const _, len)));
+            try!(cvt(libc::listen(inner.0, LISTEN_BACKLOG)));
+
+            Ok(UnixListener {
+                inner: inner,
+                unlink: UnlinkGuard(None),
+                template: None,
+                renamed: None,
+            })
+        }
+    }
+
+    /// Creates a new `UnixListener` bound to the specified socket, taking
+    /// over a stale socket file left at `path`.
+    ///
+    /// If `path` is already in use, a connection to it is attempted. If the
+    /// connection succeeds, another process is listening and an `AddrInUse`
+    /// error is returned. If it is refused, nothing is listening, so the file
+    /// is removed and the bind is retried once. The file is only removed if
+    /// it is a socket; anything else at `path` is left alone and the original
+    /// `AddrInUse` error is returned.
+    ///
+    /// This is inherently racy: another process may bind `path` between the
+    /// check and the retry, or may be about to start listening on a file
+    /// that looks stale. Only use this when no other process is expected to
+    /// be binding the same path concurrently.
+    pub fn bind_or_replace<P: AsRef<Path>>(path: P) -> io::Result<UnixListener> {
+        let path = path.as_ref();
+        match UnixListener::bind(path) {
+            Err(e) => {
+                if e.kind() != io::ErrorKind::AddrInUse {
+                    return Err(e);
+                }
+                match UnixStream::connect(path) {
+                    Ok(_) => return Err(e),
+                    Err(ref e) if e.kind() == io::ErrorKind::ConnectionRefused => {}
+                    Err(e) => return Err(e),
+                }
+                if !is_socket_file(path) {
+                    return Err(e);
+                }
+                try!(fs::remove_file(path));
+                UnixListener::bind(path)
+            }
+            res => res,
+        }
+    }
+
+    /// Creates a new `UnixListener` bound to `addr`.
+    ///
+    /// Unlike `bind`, the address namespace is chosen explicitly by the
+    /// `Address` variant rather than by a leading null byte.
+    pub fn bind_address<A: Into<Address>>(addr: A) -> io::Result<UnixListener> {
+        let (addr, len) = try!(addr.into().sockaddr());
+        unsafe {
+            let inner = try!(Inner::new(libc::SOCK_STREAM));
+
+            try!(cvt(libc::bind(inner.0, &addr as *const _ as *const _, len)));
+            try!(cvt(libc::listen(inner.0, LISTEN_BACKLOG)));
+
+            Ok(UnixListener {
+                inner: inner,
+                unlink: UnlinkGuard(None),
+                template: None,
+                renamed: None,
+            })
+        }
+    }
+
+    /// Creates a new `UnixListener` bound to `addr`, using `len` as the
+    /// address length.
+    ///
+    /// Addresses are normally passed with a length covering only the bytes
+    /// in use. For abstract addresses, though, every byte within the length
+    /// is part of the name, so a name padded with trailing null bytes is a
+    /// different address. Some peers, including some other languages'
+    /// socket libraries, always pass the full size of `sockaddr_un`; this
+    /// allows matching their convention. Bytes of `sun_path` beyond those
+    /// in use are null.
+    ///
+    /// `len` must be at least the offset of `sun_path` and at most the size
+    /// of `sockaddr_un`.
+    pub fn bind_addr_with_len(addr: &SocketAddr, len: usize) -> io::Result<UnixListener> {
+        let len = try!(addr.explicit_len(len));
+        unsafe {
+            let inner = try!(Inner::new(libc::SOCK_STREAM));
+
+            try!(cvt(libc::bind(inner.0, &addr.addr as *const _ as *const _, len)));
+            try!(cvt(libc::listen(inner.0, LISTEN_BACKLOG)));
+
+            Ok(UnixListener {
+                inner: inner,
+                unlink: UnlinkGuard(None),
+                template: None,
+                renamed: None,
+            })
+        }
+    }
+
+    /// Creates a new `UnixListener` from a listening socket inherited from
+    /// the parent process, such as one passed by systemd socket activation.
+    ///
+    /// The descriptor is checked to be a listening `AF_UNIX` stream socket
+    /// before the listener takes ownership of it, otherwise an error of kind
+    /// `InvalidInput` is returned and the descriptor is left open. The
+    /// listening state can only be checked on Linux; elsewhere that check is
+    /// skipped. Use `local_addr` to find out which address it is bound to.
+    ///
+    /// # Safety
+    ///
+    /// The caller must own `fd`. On success, ownership is transferred to the
+    /// returned `UnixListener`, and `fd` must not be closed or used
+    /// elsewhere. On error, ownership is not taken.
+    pub unsafe fn from_inherited_fd(fd: RawFd) -> io::Result<UnixListener> {
+        if try!(domain(fd)) != libc::AF_UNIX ||
+           try!(getsockopt::<libc::c_int>(fd, libc::SOL_SOCKET, libc::SO_TYPE)) != libc::SOCK_STREAM {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "file descriptor is not a Unix stream socket"));
+        }
+        #[cfg(target_os = "linux")]
+        {
+            if !try!(is_listening(fd)) {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "socket is not listening"));
+            }
+        }
+        Ok(UnixListener {
+            inner: Inner(fd),
+            unlink: UnlinkGuard(None),
+            template: None,
+            renamed: None,
+        })
+    }
+
+    /// Accepts a new incoming connection to this listener.
+    ///
+    /// This function will block the calling thread until a new Unix connection
+    /// is established. When established, the corersponding `UnixStream` and
+    /// the remote peer's address will be returned.
+    ///
+    /// The returned socket is in blocking mode, even if the listener is
+    /// nonblocking, and has close-on-exec set.
+    ///
+    /// If an accept template has been set, it is applied to the stream before
+    /// it is returned.
+    pub fn accept(&self) -> io::Result<(UnixStream, SocketAddr)> {
+        unsafe {
+            let mut fd = 0;
+            let addr = try!(SocketAddr::new(|addr, len| {
+                fd = accept_cloexec(self.inner.0, addr, len);
+                fd
+            }));
+
+            let stream = UnixStream::from_inner(Inner(fd));
+            // Some platforms copy the listener's O_NONBLOCK flag
+            #[cfg(not(target_os = "linux"))]
+            try!(stream.inner.set_nonblocking(false));
+            if let Some(ref template) = self.template {
+                try!(stream.inner.configure(template));
+            }
+            Ok((stream, addr))
+        }
+    }
+
+    /// Sets the options applied to every stream returned by `accept`.
+    pub fn set_accept_template(&mut self, template: SocketConfig) {
+        self.template = Some(template);
+    }
+
+    /// Accepts a new incoming connection without wrapping it.
+    ///
+    /// This is a building block for integrating with custom event loops.
+    /// The accepted descriptor is nonblocking and close-on-exec, and the
+    /// caller is responsible for closing it. If no connection is pending,
+    /// `None` is returned. The listener should be in nonblocking mode, or
+    /// this will block until a connection arrives.
+    pub fn accept_raw(&self) -> io::Result<Option<(RawFd, SocketAddr)>> {
+        let mut fd = -1;
+        let res = SocketAddr::new(|addr, len| {
+            unsafe {
+                fd = accept_nonblocking(self.inner.0, addr, len);
+                fd
+            }
+        });
+
+        match res {
+            Ok(addr) => Ok(Some((fd, addr))),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates a new independently owned handle to the underlying socket.
+    ///
+    /// The returned `UnixListener` is a reference to the same socket that this
+    /// object references. Both handles can be used to accept incoming
+    /// connections and options set on one listener will affect the other.
+    ///
+    /// The clone doesn't remove the socket file when dropped, even if
+    /// `set_unlink_on_drop` was enabled on this listener.
+    pub fn try_clone(&self) -> io::Result<UnixListener> {
+        Ok(UnixListener {
+            inner: try!(self.inner.try_clone()),
+            unlink: UnlinkGuard(None),
+            template: self.template.clone(),
+            renamed: self.renamed.clone(),
+        })
+    }
+
+    /// Returns the local socket address of this listener.
+    ///
+    /// For a listener created by `bind_atomic`, this is the path its socket
+    /// file was renamed to rather than the temporary path it was bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        if let Some(ref addr) = self.renamed {
+            return Ok(addr.clone());
+        }
+        SocketAddr::new(|addr, len| unsafe { libc::getsockname(self.inner.0, addr, len) })
+    }
+
+    /// Returns the device and inode numbers of the listener's socket file.
+    ///
+    /// These identify the file regardless of how its path is spelled, so
+    /// they can be used to tell whether two listeners are bound to the same
+    /// file. The path is looked up again, so the result reflects whatever is
+    /// at the bound path now, and a relative path is resolved against the
+    /// current working directory. For a listener created by `bind_atomic`,
+    /// the path looked up is the one the socket file was renamed to.
+    ///
+    /// An error of kind `InvalidInput` is returned if the listener is not
+    /// bound to a pathname address.
+    pub fn file_ident(&self) -> io::Result<(u64, u64)> {
+        let addr = try!(self.local_addr());
+        let path = match addr.as_pathname() {
+            Some(path) => path,
+            None => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "listener is not bound to a pathname"))
+            }
+        };
+        let metadata = try!(fs::metadata(path));
+        Ok((metadata.dev(), metadata.ino()))
+    }
+
+    /// Sets whether the listener's socket file is removed from the filesystem
+    /// when the listener is dropped.
+    ///
+    /// This is disabled by default. Enabling it records the listener's
+    /// current pathname address, and fails if the listener is not bound to
+    /// one.
+    ///
+    /// Only this handle removes the file. Clones made with `try_clone` never
+    /// do, whether they were made before or after this is enabled, so
+    /// dropping a clone can't remove a file still in use by the original.
+    ///
+    /// For a listener created by `bind_atomic`, the path recorded is the one
+    /// the socket file was renamed to. If another listener has since replaced
+    /// the file there, dropping this one removes the replacement.
+    pub fn set_unlink_on_drop(&mut self, unlink: bool) -> io::Result<()> {
+        let addr = self.local_addr();
+        self.unlink.set(unlink, addr)
+    }
+
+    /// Cancels a previous request to remove the listener's socket file when
+    /// it is dropped.
+    ///
+    /// This is useful when ownership of the socket is passed elsewhere and
+    /// the file must be kept.
+    pub fn forget_unlink(&mut self) {
+        self.unlink.0 = None;
+    }
+
+    /// Moves the socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.inner.set_nonblocking(nonblocking)
+    }
+
+    /// Returns whether the socket is in nonblocking mode.
+    ///
+    /// This reads the descriptor's `O_NONBLOCK` flag, so it reflects the
+    /// mode however it was set, including by a constructor.
+    pub fn nonblocking(&self) -> io::Result<bool> {
+        self.inner.nonblocking()
+    }
+
+    /// Moves the socket into or out of nonblocking mode, returning whether
+    /// it was previously in nonblocking mode.
+    ///
+    /// The mode is only changed if it differs from the requested one.
+    pub fn swap_nonblocking(&self, nonblocking: bool) -> io::Result<bool> {
+        self.inner.swap_nonblocking(nonblocking)
+    }
+
+    /// Returns the value of the `SO_ERROR` option.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.inner.take_error()
+    }
+
+    /// Returns the file descriptor flags of the socket, as reported by
+    /// `fcntl(F_GETFD)`.
+    ///
+    /// This is useful for tracking down descriptor leaks, since a socket
+    /// without `FD_CLOEXEC` set will be inherited by child processes.
+    pub fn fd_flags(&self) -> io::Result<i32> {
+        self.inner.fd_flags()
+    }
+
+    /// Returns the type of the socket, as reported by `SO_TYPE`.
+    ///
+    /// This is useful for checking a socket created with `from_raw_fd`.
+    pub fn socket_type(&self) -> io::Result<SocketType> {
+        self.inner.socket_type()
+    }
+
+    /// Returns whether this handle and `other` refer to the same socket.
+    ///
+    /// Handles made with `try_clone`, or received from another process
+    /// which passed this socket, refer to the same socket, while separate
+    /// connections never do. On Linux this compares the open files with
+    /// `kcmp`; where that is unavailable, and on other platforms, the
+    /// sockets' inode numbers are compared instead.
+    pub fn same_socket(&self, other: &UnixListener) -> io::Result<bool> {
+        self.inner.same_socket(&other.inner)
+    }
+
+    /// Closes the socket, returning any error reported by `close`.
+    ///
+    /// Dropping the socket also closes it but silently ignores errors. The
+    /// descriptor is released even if an error is returned.
+    pub fn close(self) -> io::Result<()> {
+        self.inner.close()
+    }
+
+    /// Returns the maximum number of pending connections the kernel will
+    /// queue for this listener.
+    ///
+    /// This is the backlog passed to `listen`, as clamped by the kernel. It
+    /// is currently only available on Linux, where it is read through the
+    /// `sock_diag` netlink interface; other platforms return an error.
+    pub fn backlog(&self) -> io::Result<usize> {
+        self.inner.listen_queues().map(|(_, backlog)| backlog)
+    }
+
+    /// Sets the maximum number of pending connections the kernel will queue
+    /// for this listener.
+    ///
+    /// This calls `listen` again with the new backlog, which the kernel
+    /// permits on a socket that is already listening. It allows a listener to
+    /// be bound early to reserve its address and given more capacity once it
+    /// begins serving.
+    pub fn set_backlog(&self, backlog: i32) -> io::Result<()> {
+        unsafe { cvt(libc::listen(self.inner.0, backlog)).map(|_| ()) }
+    }
+
+    /// Returns an iterator over incoming connections.
+    ///
+    /// The iterator will never return `None` and will also not yield the
+    /// peer's `SocketAddr` structure.
+    ///
+    /// Each stream is accepted as by `accept`, so any accept template is
+    /// applied to it. A failure to apply the template is yielded as an
+    /// `Err` item.
+    pub fn incoming<'a>(&'a self) -> IncomingStream<'a> {
+        IncomingStream { listener: self }
+    }
+}
+
+impl AsRawFd for UnixListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.0
+    }
+}
+
+impl FromRawFd for UnixListener {
+    unsafe fn from_raw_fd(fd: RawFd) -> UnixListener {
+        UnixListener {
+            inner: Inner(fd),
+            unlink: UnlinkGuard(None),
+            template: None,
+            renamed: None,
+        }
+    }
+}
+
+impl IntoRawFd for UnixListener {
+    fn into_raw_fd(mut self) -> RawFd {
+        self.forget_unlink();
+        let fd = self.inner.0;
+        mem::forget(self);
+        fd
+    }
+}
+
+impl<'a> IntoIterator for &'a UnixListener {
+    type Item = io::Result<UnixStream>;
+    type IntoIter = IncomingStream<'a>;
+
+    fn into_iter(self) -> IncomingStream<'a> {
+        self.incoming()
+    }
+}
+
+/// An iterator over incoming connections to a `UnixListener`.
+///
+/// It will never return `None`.
+#[derive(Debug)]
+pub struct IncomingStream<'a> {
+    listener: &'a UnixListener,
+}
+
+impl<'a> Iterator for IncomingStream<'a> {
+    type Item = io::Result<UnixStream>;
+
+    fn next(&mut self) -> Option<io::Result<UnixStream>> {
+        Some(self.listener.accept().map(|s| s.0))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::max_value(), None)
+    }
+}
+
+/// A Unix datagram socket.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use unix_socket::UnixDatagram;
+///
+/// let socket = UnixDatagram::bind("/path/to/my/socket").unwrap();
+/// socket.send_to(b"hello world", "/path/to/other/socket").unwrap();
+/// let mut buf = [0; 100];
+/// let (count, address) = socket.recv_from(&mut buf).unwrap();
+/// println!("socket {:?} sent {:?}", address, &buf[..count]);
+/// ```
+pub struct UnixDatagram {
+    inner: Inner,
+    unlink: UnlinkGuard,
+    // The bound address, once known to be named
+    local: Mutex<Option<SocketAddr>>,
+}
+
+impl fmt::Debug for UnixDatagram {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut builder = fmt.debug_struct("UnixDatagram");
+        builder.field("fd", &self.inner.0);
+        if let Ok(flags) = self.inner.fd_flags() {
+            builder.field("cloexec", &(flags & libc::FD_CLOEXEC != 0));
+        }
+        if let Ok(accepting) = self.inner.accepting() {
+            builder.field("accepting", &accepting);
+        }
+        if let Ok(addr) = self.local_addr() {
+            builder.field("local", &addr);
+        }
+        if let Ok(addr) = self.peer_addr() {
+            builder.field("peer", &addr);
+        }
+        builder.finish()
+    }
+}
+
+impl UnixDatagram {
+    /// Creates a Unix datagram socket bound to the given path.
+    ///
+    /// Linux provides, as a nonportable extension, a separate "abstract"
+    /// address namespace as opposed to filesystem-based addressing. If `path`
+    /// begins with a null byte, it will be interpreted as an "abstract"
+    /// address. Otherwise, it will be interpreted as a "pathname" address,
+    /// corresponding to a path on the filesystem.
+    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<UnixDatagram> {
+        unsafe {
+            let inner = try!(Inner::new(libc::SOCK_DGRAM));
+            let (addr, len) = try!(sockaddr_un(path));
+
+            try!(cvt(libc::bind(inner.0, &addr as *const _ as *const _, len)));
+
+            Ok(UnixDatagram {
+                inner: inner,
+                unlink: UnlinkGuard(None),
+                local: Mutex::new(None),
+            })
+        }
+    }
+
+    /// Creates a Unix datagram socket bound to `addr`.
+    ///
+    /// Unlike `bind`, the address namespace is chosen explicitly by the
+    /// `Address` variant rather than by a leading null byte.
+    pub fn bind_address<A: Into<Address>>(addr: A) -> io::Result<UnixDatagram> {
+        let (addr, len) = try!(addr.into().sockaddr());
+        unsafe {
+            let inner = try!(Inner::new(libc::SOCK_DGRAM));
+
+            try!(cvt(libc::bind(inner.0, &addr as *const _ as *const _, len)));
+
+            Ok(UnixDatagram {
+                inner: inner,
+                unlink: UnlinkGuard(None),
+                local: Mutex::new(None),
+            })
+        }
+    }
+
+    /// Creates a Unix datagram socket bound to `local` and connected to
+    /// `peer`.
+    ///
+    /// The socket is bound before it is connected, so the peer sees `local`
+    /// as the source of everything sent with `send`, and may reply to it.
+    /// If the connection fails, a socket file created at `local` is removed
+    /// again.
+    pub fn bind_and_connect<P, Q>(local: P, peer: Q) -> io::Result<UnixDatagram>
+        where P: AsRef<Path>,
+              Q: AsRef<Path>
+    {
+        let socket = try!(UnixDatagram::bind(local));
+        if let Err(e) = socket.connect(peer) {
+            if let Ok(addr) = socket.local_addr() {
+                if let Some(path) = addr.as_pathname() {
+                    let _ = fs::remove_file(path);
+                }
+            }
+            return Err(e);
+        }
+        Ok(socket)
+    }
+
+    /// Creates a Unix datagram socket bound to the given path, which is
+    /// removed from the filesystem when the socket is dropped.
+    ///
+    /// Unlike listeners, where removal on drop must be requested with
+    /// `set_unlink_on_drop`, this enables it from the start. It may be
+    /// cancelled with `forget_unlink`. Clones made with `try_clone` do not
+    /// remove the file. The path must be a pathname address.
+    pub fn bind_cleanup<P: AsRef<Path>>(path: P) -> io::Result<UnixDatagram> {
+        let mut socket = try!(UnixDatagram::bind(path));
+        let addr = socket.local_addr();
+        try!(socket.unlink.set(true, addr));
+        Ok(socket)
+    }
+
+    /// Cancels removal of the socket's file when it is dropped.
+    ///
+    /// This is useful when ownership of the socket is passed elsewhere and
+    /// the file must be kept.
+    pub fn forget_unlink(&mut self) {
+        self.unlink.0 = None;
+    }
+
+    /// Creates a Unix Datagram socket which is not bound to any address.
+    pub fn unbound() -> io::Result<UnixDatagram> {
+        let inner = try!(Inner::new(libc::SOCK_DGRAM));
+        Ok(UnixDatagram {
+            inner: inner,
+            unlink: UnlinkGuard(None),
+            local: Mutex::new(None),
+        })
+    }
+
+    /// Create an unnamed pair of connected sockets.
+    ///
+    /// Returns two `UnixDatagrams`s which are connected to each other. Both have
+    /// close-on-exec set.
+    pub fn pair() -> io::Result<(UnixDatagram, UnixDatagram)> {
+        let (i1, i2) = try!(Inner::new_pair(libc::SOCK_DGRAM));
+        let s1 = UnixDatagram {
+            inner: i1,
+            unlink: UnlinkGuard(None),
+            local: Mutex::new(None),
+        };
+        let s2 = UnixDatagram {
+            inner: i2,
+            unlink: UnlinkGuard(None),
+            local: Mutex::new(None),
+        };
+        Ok((s1, s2))
+    }
+
+    /// Creates a Unix datagram socket with a private address of its own,
+    /// connected to `server`.
+    ///
+    /// This packages the common request/reply pattern: requests can be sent
+    /// to the server with `send`, and since the socket has an address the
+    /// server can reply to it with `send_to_addr`.
+    ///
+    /// On Linux the socket is autobound to a unique abstract address. On
+    /// other platforms it is bound to a unique path in the system temporary
+    /// directory, which is removed when the socket is dropped.
+    pub fn bind_to_reply<P: AsRef<Path>>(server: P) -> io::Result<UnixDatagram> {
+        let socket = try!(UnixDatagram::reply_socket());
+        try!(socket.connect(server));
+        Ok(socket)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn reply_socket() -> io::Result<UnixDatagram> {
+        unsafe {
+            let inner = try!(Inner::new(libc::SOCK_DGRAM));
+            let mut addr: libc::sockaddr_un = mem::zeroed();
+            addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+
+            // binding with only the family asks the kernel to pick an address
+            try!(cvt(libc::bind(inner.0,
+                                &addr as *const _ as *const _,
+                                sun_path_offset() as libc::socklen_t)));
+
+            Ok(UnixDatagram {
+                inner: inner,
+                unlink: UnlinkGuard(None),
+                local: Mutex::new(None),
+            })
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn reply_socket() -> io::Result<UnixDatagram> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let name = format!("unix_socket-reply-{}-{}",
+                           unsafe { libc::getpid() },
+                           COUNTER.fetch_add(1, atomic::Ordering::Relaxed));
+        UnixDatagram::bind_cleanup(env::temp_dir().join(name))
+    }
+
+    /// Connects the socket to the specified address.
+    ///
+    /// The `send` method may be used to send data to the specified address.
+    /// `recv` and `recv_from` will only receive data from that address.
+    pub fn connect<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.inner.connect(path)
+    }
+
+    /// Creates a new independently owned handle to the underlying socket.
+    ///
+    /// The returned `UnixListener` is a reference to the same socket that this
+    /// object references. Both handles can be used to accept incoming
+    /// connections and options set on one listener will affect the other.
+    pub fn try_clone(&self) -> io::Result<UnixDatagram> {
+        Ok(UnixDatagram {
+            inner: try!(self.inner.try_clone()),
+            unlink: UnlinkGuard(None),
+            local: Mutex::new(None),
+        })
+    }
+
+    /// Returns the address of this socket.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        SocketAddr::new(|addr, len| unsafe { libc::getsockname(self.inner.0, addr, len) })
+    }
+
+    // Returns the local address, querying it only until it is named since a
+    // named address can't change.
+    fn cached_local_addr(&self) -> io::Result<SocketAddr> {
+        let mut local = self.local.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(ref addr) = *local {
+            return Ok(addr.clone());
+        }
+        let addr = try!(self.local_addr());
+        if !addr.is_unnamed() {
+            *local = Some(addr.clone());
+        }
+        Ok(addr)
+    }
+
+    /// Returns the address of this socket, or `None` if it is not bound to
+    /// one.
+    ///
+    /// Unlike `local_addr`, an unnamed address and any error are both
+    /// reported as `None`, which is convenient when displaying the address.
+    pub fn local_addr_opt(&self) -> Option<SocketAddr> {
+        match self.local_addr() {
+            Ok(ref addr) if addr.is_unnamed() => None,
+            Ok(addr) => Some(addr),
+            Err(_) => None,
+        }
+    }
+
+    /// Returns the address of this socket's peer.
+    ///
+    /// The `connect` method will connect the socket to a peer.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        SocketAddr::new(|addr, len| unsafe { libc::getpeername(self.inner.0, addr, len) })
+    }
+
+    /// Receives data from the socket.
+    ///
+    /// On success, returns the number of bytes read and the address from
+    /// whence the data came.
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.inner.recv_from(buf, 0)
+    }
+
+    /// Receives data from the socket into a possibly uninitialized buffer.
+    ///
+    /// This avoids zeroing a large receive buffer before each call. On
+    /// success, returns the number of bytes read and the address from whence
+    /// the data came; only that many bytes at the start of `buf` have been
+    /// initialized.
+    pub fn recv_buf_from(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<(usize, SocketAddr)> {
+        self.inner.recv_from_uninit(buf, 0)
+    }
+
+    /// Receives a single datagram into a newly allocated buffer.
+    ///
+    /// The length of the next datagram is determined by peeking at it before
+    /// it is received, so the buffer is exactly large enough to hold it: the
+    /// datagram is never truncated and no excess memory is allocated. If
+    /// another handle to this socket receives concurrently, the datagram
+    /// received may not be the one that was peeked.
+    ///
+    /// On success, returns the datagram and the address from whence it came.
+    pub fn recv_vec(&self) -> io::Result<(Vec<u8>, SocketAddr)> {
+        let len = try!(self.inner.peek_datagram_len(0));
+        let mut buf = vec![0; len];
+        let (count, addr) = try!(self.inner.recv_from(&mut buf, 0));
+        buf.truncate(count);
+        Ok((buf, addr))
+    }
+
+    /// Receives a whole datagram from the socket into a newly allocated
+    /// buffer, along with the addresses of both ends.
+    ///
+    /// Unix sockets don't report which address a datagram was sent to, so
+    /// the local address is simply this socket's own, as returned by
+    /// `local_addr`. It is looked up once and then cached, which is cheaper
+    /// for code which logs both ends of every datagram.
+    ///
+    /// On success, returns the data, the address from whence it came, and
+    /// the local address.
+    pub fn recv_from_full_addr(&self) -> io::Result<(Vec<u8>, SocketAddr, SocketAddr)> {
+        let (buf, addr) = try!(self.recv_vec());
+        let local = try!(self.cached_local_addr());
+        Ok((buf, addr, local))
+    }
+
+    /// Receives every datagram currently queued on the socket without
+    /// blocking.
+    ///
+    /// `f` is called with each datagram and the address from whence it came,
+    /// in the order they were queued. Receiving stops once no more datagrams
+    /// are immediately available, regardless of whether the socket is in
+    /// nonblocking mode.
+    ///
+    /// On success, returns the number of datagrams received.
+    pub fn drain<F>(&self, mut f: F) -> io::Result<usize>
+        where F: FnMut(&[u8], &SocketAddr)
+    {
+        let mut buf = vec![];
+        let mut count = 0;
+        loop {
+            let res = self.inner
+                          .peek_datagram_len(libc::MSG_DONTWAIT)
+                          .and_then(|len| {
+                              buf.resize(len, 0);
+                              self.inner.recv_from(&mut buf, libc::MSG_DONTWAIT)
+                          });
+            match res {
+                Ok((len, addr)) => {
+                    f(&buf[..len], &addr);
+                    count += 1;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(count),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Receives a single datagram of at most `max` bytes into a newly
+    /// allocated buffer.
+    ///
+    /// No more than `max` bytes are ever allocated. If the datagram is larger
+    /// than that, it is discarded and an error of kind `InvalidData` is
+    /// returned rather than silently truncating it. On Linux the error
+    /// message includes the real size of the datagram.
+    ///
+    /// On success, returns the datagram and the address from whence it came.
+    pub fn recv_from_bounded(&self, max: usize) -> io::Result<(Vec<u8>, SocketAddr)> {
+        let mut buf = vec![0; max];
+        let msg = try!(self.inner.recv_msg(&mut [IoSliceMut::new(&mut buf)], &mut [], RECV_TRUNC));
+        if msg.flags & libc::MSG_TRUNC != 0 {
+            let err = if msg.count > max {
+                format!("datagram of {} bytes exceeds the maximum of {} bytes", msg.count, max)
+            } else {
+                format!("datagram exceeds the maximum of {} bytes", max)
+            };
+            return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+        }
+        buf.truncate(msg.count);
+        Ok((buf, msg.addr))
+    }
+
+    /// Receives data and ancillary data from the socket.
+    ///
+    /// The data, the sender's address, and any control messages (such as
+    /// passed file descriptors or, on Linux, the sender's credentials when
+    /// `SO_PASSCRED` is enabled) are all read by a single `recvmsg` call.
+    /// Received file descriptors have close-on-exec set.
+    ///
+    /// On success, returns the number of bytes read, the address from whence
+    /// the data came, and the ancillary data.
+    pub fn recv_from_ancillary(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, Ancillary)> {
+        let mut control = vec![0; ANCILLARY_CAPACITY];
+        let msg = try!(self.inner.recv_msg(&mut [IoSliceMut::new(buf)], &mut control, 0));
+        let ancillary = Ancillary::parse(&control[..msg.control_len], msg.flags);
+        Ok((msg.count, msg.addr, ancillary))
+    }
+
+    /// Receives data and ancillary data from the socket into caller-supplied
+    /// buffers.
+    ///
+    /// This is a lower-level form of `recv_from_ancillary` which allows a
+    /// control buffer to be reused across calls rather than allocated for
+    /// each one. Control data which does not fit in `control` is discarded.
+    /// The written prefix of `control` may be parsed with
+    /// `Ancillary::from_control`. Received file descriptors have
+    /// close-on-exec set.
+    ///
+    /// On success, returns the number of bytes read, the number of bytes of
+    /// control data written, and the address from whence the data came.
+    pub fn recv_with_control(&self,
+                             buf: &mut [u8],
+                             control: &mut [u8])
+                             -> io::Result<(usize, usize, SocketAddr)> {
+        let msg = try!(self.inner.recv_msg(&mut [IoSliceMut::new(buf)], control, 0));
+        Ok((msg.count, msg.control_len, msg.addr))
+    }
+
+    /// Receives data from the socket.
+    ///
+    /// On success, returns the number of bytes read.
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.recv(buf)
+    }
+
+    /// Receives data from the socket, waiting no later than `deadline`.
+    ///
+    /// This is convenient when several operations share an overall
+    /// deadline, since the remaining time doesn't need to be recomputed for
+    /// each one. If no data arrives before `deadline`, or it has already
+    /// passed, an error of kind `TimedOut` is returned. The socket's read
+    /// timeout is not used.
+    ///
+    /// On success, returns the number of bytes read.
+    pub fn recv_deadline(&self, buf: &mut [u8], deadline: Instant) -> io::Result<usize> {
+        self.inner.recv_deadline(buf, deadline)
+    }
+
+    /// Sends data on the socket to the specified address.
+    ///
+    /// On success, returns the number of bytes written.
+    ///
+    /// A datagram is sent atomically, either in its entirety or not at all,
+    /// so there is never a partial send to resume. A send interrupted by a
+    /// signal before the datagram was transmitted is transparently retried.
+    /// An empty `buf` sends a zero-length datagram, which the receiver sees
+    /// as a receive of zero bytes.
+    ///
+    /// Under memory pressure the send may fail with `ENOBUFS`. Like
+    /// `EAGAIN`, this is a transient condition rather than a fatal one; see
+    /// `is_transient` and `send_to_retrying`.
+    pub fn send_to<P: AsRef<Path>>(&self, buf: &[u8], path: P) -> io::Result<usize> {
+        unsafe {
+            let (addr, len) = try!(sockaddr_un(path));
+            self.inner.send_to(buf, &addr, len, 0)
+        }
+    }
+
+    /// Sends data on the socket to the specified address without blocking.
+    ///
+    /// The send is made with `MSG_DONTWAIT`, regardless of whether the
+    /// socket is in nonblocking mode. If it can't be made without blocking,
+    /// because the receiver's queue is full, or fails with `ENOBUFS`, `None`
+    /// is returned so that backpressure can be handled without inspecting
+    /// the error.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn try_send_to<P: AsRef<Path>>(&self, buf: &[u8], path: P) -> io::Result<Option<usize>> {
+        let res = unsafe {
+            let (addr, len) = try!(sockaddr_un(path));
+            self.inner.send_to(buf, &addr, len, libc::MSG_DONTWAIT)
+        };
+        match res {
+            Ok(count) => Ok(Some(count)),
+            Err(ref e) if is_transient(e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sends data on the socket to the specified address.
+    ///
+    /// This is useful for replying to the address returned by `recv_from`,
+    /// which may be an autobound abstract address.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_to_addr(&self, buf: &[u8], addr: &SocketAddr) -> io::Result<usize> {
+        self.inner.send_to(buf, &addr.addr, addr.len, 0)
+    }
+
+    /// Sends data on the socket to the specified address, retrying on
+    /// transient errors.
+    ///
+    /// Up to `attempts` sends are made, backing off briefly between each,
+    /// while the send fails with an error for which `is_transient` returns
+    /// `true`. The last error is returned if every attempt fails. At least one
+    /// attempt is always made.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_to_retrying<P: AsRef<Path>>(&self,
+                                            buf: &[u8],
+                                            path: P,
+                                            attempts: usize)
+                                            -> io::Result<usize> {
+        let path = path.as_ref();
+        let mut backoff = Duration::from_millis(1);
+        let mut attempt = 1;
+        loop {
+            match self.send_to(buf, path) {
+                Err(ref e) if attempt < attempts && is_transient(e) => {}
+                res => return res,
+            }
+            thread::sleep(backoff);
+            backoff = cmp::min(backoff * 2, Duration::from_millis(64));
+            attempt += 1;
+        }
+    }
+
+    /// Sends data on the socket to the socket's peer.
+    ///
+    /// The peer address may be set by the `connect` method, and this method
+    /// will return an error if the socket has not already been connected.
+    ///
+    /// As with `send_to`, a send interrupted by a signal is retried, and an
+    /// empty `buf` sends a zero-length datagram.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.send(buf)
+    }
+
+    /// Attempts to send data on the socket without blocking, for use in
+    /// hand-written futures.
+    ///
+    /// The send is made with `MSG_DONTWAIT`. If it would block,
+    /// `Poll::Pending` is returned; no wakeup is registered, so the caller's
+    /// reactor must arrange to poll again once the socket is writable.
+    /// Otherwise the result of the send is returned as `Poll::Ready`.
+    pub fn try_send_poll(&self, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.inner.try_send_poll(buf)
+    }
+
+    /// Sends a zero-length datagram to the socket's peer.
+    ///
+    /// This is a common way to wake a peer which is waiting to receive.
+    pub fn notify(&self) -> io::Result<()> {
+        self.inner.send(&[]).map(|_| ())
+    }
+
+    /// Sends data on the socket to the connected peer along with file descriptors.
+    ///
+    /// The descriptors are passed to the peer with `SCM_RIGHTS` and remain
+    /// open in this process.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_fds(&self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        self.inner.send_fds(buf, fds)
+    }
+
+    /// Sends a batch of file descriptors to the peer in a single message.
+    ///
+    /// All of the descriptors are passed in one `SCM_RIGHTS` control message
+    /// accompanied by a single byte of data, which is cheaper than passing
+    /// them one at a time. The receiver gets them all from one `recv_fds`.
+    /// Linux limits a single message to 253 descriptors.
+    pub fn send_fd_batch(&self, fds: &[RawFd]) -> io::Result<()> {
+        self.inner.send_fds(&[0], fds).map(|_| ())
+    }
+
+    /// Receives data from the socket along with any file descriptors passed
+    /// by the peer.
+    ///
+    /// The received descriptors are owned by the caller and have
+    /// close-on-exec set.
+    ///
+    /// On success, returns the number of bytes read and the descriptors.
+    pub fn recv_fds(&self, buf: &mut [u8]) -> io::Result<(usize, Vec<RawFd>)> {
+        let (msg, fds) = try!(self.inner.recv_fds(&mut [IoSliceMut::new(buf)]));
+        Ok((msg.count, fds))
+    }
+
+    /// Receives a datagram into a series of buffers along with any file
+    /// descriptors passed by the peer and the address it came from.
+    ///
+    /// Received descriptors are appended to `fds`. They are owned by the
+    /// caller and have close-on-exec set.
+    ///
+    /// On success, returns the number of bytes read and the address from
+    /// whence the data came.
+    pub fn recv_vectored_from_fds(&self,
+                                  bufs: &mut [IoSliceMut],
+                                  fds: &mut Vec<RawFd>)
+                                  -> io::Result<(usize, SocketAddr)> {
+        let (msg, received) = try!(self.inner.recv_fds(bufs));
+        fds.extend(received);
+        Ok((msg.count, msg.addr))
+    }
+
+    /// Sets the read timeout for the socket.
+    ///
+    /// If the provided value is `None`, then `recv` and `recv_from` calls will
+    /// block indefinitely. It is an error to pass the zero `Duration` to this
+    /// method.
+    ///
+    /// A receive that times out fails with an error of kind `TimedOut`.
+    /// The `EAGAIN` reported by the operating system is translated so that
+    /// timeouts can be told apart from nonblocking receives, which fail with
+    /// `WouldBlock`.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeout(timeout, libc::SO_RCVTIMEO)
+    }
+
+    /// Sets the read timeout for the socket, treating a zero `Duration` as a
+    /// request for nonblocking mode.
+    ///
+    /// Passing `Some` of the zero `Duration` clears the read timeout and puts
+    /// the socket into nonblocking mode, so reads fail with `WouldBlock`
+    /// rather than waiting. Any other value sets the read timeout as
+    /// `set_read_timeout` does and puts the socket into blocking mode. Note
+    /// that nonblocking mode applies to writes as well as reads.
+    pub fn set_read_timeout_or_nonblocking(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_read_timeout_or_nonblocking(timeout)
+    }
+
+    /// Sets the write timeout for the socket.
+    ///
+    /// If the provided value is `None`, then `send` and `send_to` calls will
+    /// block indefinitely. It is an error to pass the zero `Duration` to this
+    /// method.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeout(timeout, libc::SO_SNDTIMEO)
+    }
+
+    /// Returns the read timeout of this socket.
+    ///
+    /// This is the value stored by the kernel rather than the one passed to
+    /// `set_read_timeout`. Timeouts are stored with at most microsecond
+    /// resolution, so any finer part is truncated, and Linux stores them as
+    /// a whole number of clock ticks, so short timeouts may be rounded up.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.inner.timeout(libc::SO_RCVTIMEO)
+    }
+
+    /// Returns the write timeout of this socket.
+    ///
+    /// This is the value stored by the kernel rather than the one passed to
+    /// `set_write_timeout`. Timeouts are stored with at most microsecond
+    /// resolution, so any finer part is truncated, and Linux stores them as
+    /// a whole number of clock ticks, so short timeouts may be rounded up.
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        self.inner.timeout(libc::SO_SNDTIMEO)
+    }
+
+    /// Sets the read and write timeouts for the socket together.
+    ///
+    /// The values are interpreted as by `set_read_timeout` and
+    /// `set_write_timeout`. If the write timeout can't be set, the read
+    /// timeout is restored to its previous value, so the socket is never
+    /// left with only one of them changed.
+    pub fn set_timeouts(&self, read: Option<Duration>, write: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeouts(read, write)
+    }
+
+    /// Returns the read and write timeouts of this socket.
+    pub fn timeouts(&self) -> io::Result<(Option<Duration>, Option<Duration>)> {
+        let read = try!(self.inner.timeout(libc::SO_RCVTIMEO));
+        let write = try!(self.inner.timeout(libc::SO_SNDTIMEO));
+        Ok((read, write))
+    }
+
+    /// Sets the size of the socket's send buffer.
+    ///
+    /// The kernel may adjust the requested size; on Linux it is doubled to
+    /// leave room for bookkeeping overhead and capped by the system
+    /// `wmem_max` limit.
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.inner.set_buffer_size(size, libc::SO_SNDBUF)
+    }
+
+    /// Returns the size of the socket's send buffer.
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.inner.buffer_size(libc::SO_SNDBUF)
+    }
+
+    /// Sets the size of the socket's receive buffer.
+    ///
+    /// The kernel may adjust the requested size; on Linux it is doubled to
+    /// leave room for bookkeeping overhead and capped by the system
+    /// `rmem_max` limit.
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.inner.set_buffer_size(size, libc::SO_RCVBUF)
+    }
+
+    /// Returns the size of the socket's receive buffer.
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        self.inner.buffer_size(libc::SO_RCVBUF)
+    }
+
+    /// Returns the size of the socket's receive buffer along with the system
+    /// limit on the size which may be requested.
+    ///
+    /// This shows whether a requested size was clamped. On Linux the limit
+    /// is `net.core.rmem_max`, and because the kernel doubles requested
+    /// sizes the buffer may be up to twice the limit. Other platforms return
+    /// an error.
+    pub fn recv_buffer_limits(&self) -> io::Result<(usize, usize)> {
+        self.inner.recv_buffer_limits()
+    }
+
+    /// Sets the value of the `SO_KEEPALIVE` option on this socket.
+    ///
+    /// Unix domain sockets have no keepalive probes, so this typically has
+    /// no effect. It is accepted for uniformity with code that configures
+    /// TCP sockets the same way.
+    pub fn set_keepalive(&self, keepalive: bool) -> io::Result<()> {
+        self.inner.setsockopt(libc::SOL_SOCKET, libc::SO_KEEPALIVE, keepalive as libc::c_int)
+    }
+
+    /// Returns the value of the `SO_KEEPALIVE` option on this socket.
+    pub fn keepalive(&self) -> io::Result<bool> {
+        self.inner.getsockopt::<libc::c_int>(libc::SOL_SOCKET, libc::SO_KEEPALIVE).map(|v| v != 0)
+    }
+
+    /// Moves the socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.inner.set_nonblocking(nonblocking)
+    }
+
+    /// Returns whether the socket is in nonblocking mode.
+    ///
+    /// This reads the descriptor's `O_NONBLOCK` flag, so it reflects the
+    /// mode however it was set, including by a constructor.
+    pub fn nonblocking(&self) -> io::Result<bool> {
+        self.inner.nonblocking()
+    }
+
+    /// Moves the socket into or out of nonblocking mode, returning whether
+    /// it was previously in nonblocking mode.
+    ///
+    /// The mode is only changed if it differs from the requested one.
+    pub fn swap_nonblocking(&self, nonblocking: bool) -> io::Result<bool> {
+        self.inner.swap_nonblocking(nonblocking)
+    }
+
+    /// Applies the options set in `cfg` to the socket.
+    ///
+    /// The options are applied in the order of `SocketConfig`'s fields. If
+    /// one can't be set, the error identifies it, and the options before it
+    /// are restored to their previous values on a best-effort basis.
+    pub fn configure(&self, cfg: &SocketConfig) -> Result<(), ConfigError> {
+        self.inner.configure(cfg)
+    }
+
+    /// Returns the value of the `SO_ERROR` option.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.inner.take_error()
+    }
+
+    /// Returns the file descriptor flags of the socket, as reported by
+    /// `fcntl(F_GETFD)`.
+    ///
+    /// This is useful for tracking down descriptor leaks, since a socket
+    /// without `FD_CLOEXEC` set will be inherited by child processes.
+    pub fn fd_flags(&self) -> io::Result<i32> {
+        self.inner.fd_flags()
+    }
+
+    /// Returns the type of the socket, as reported by `SO_TYPE`.
+    ///
+    /// This is useful for checking a socket created with `from_raw_fd`.
+    pub fn socket_type(&self) -> io::Result<SocketType> {
+        self.inner.socket_type()
+    }
+
+    /// Returns whether this handle and `other` refer to the same socket.
+    ///
+    /// Handles made with `try_clone`, or received from another process
+    /// which passed this socket, refer to the same socket, while separate
+    /// connections never do. On Linux this compares the open files with
+    /// `kcmp`; where that is unavailable, and on other platforms, the
+    /// sockets' inode numbers are compared instead.
+    pub fn same_socket(&self, other: &UnixDatagram) -> io::Result<bool> {
+        self.inner.same_socket(&other.inner)
+    }
+
+    /// Waits for the socket to become readable or writable.
+    ///
+    /// If `timeout` is `None`, this blocks until the socket is ready.
+    /// Otherwise it waits at most `timeout`, and the returned `Ready` will
+    /// report neither readable nor writable if it elapsed first.
+    pub fn poll_ready(&self, timeout: Option<Duration>) -> io::Result<Ready> {
+        self.inner.poll(libc::POLLIN | libc::POLLOUT, timeout)
+    }
+
+    /// Returns `true` if a read from the socket would not block.
+    pub fn readable(&self) -> io::Result<bool> {
+        self.inner.poll(libc::POLLIN, Some(Duration::from_secs(0))).map(|r| r.is_readable())
+    }
+
+    /// Returns `true` if a write to the socket would not block.
+    pub fn writable(&self) -> io::Result<bool> {
+        self.inner.poll(libc::POLLOUT, Some(Duration::from_secs(0))).map(|r| r.is_writable())
+    }
+
+    /// Closes the socket, returning any error reported by `close`.
+    ///
+    /// Dropping the socket also closes it but silently ignores errors. The
+    /// descriptor is released even if an error is returned.
+    pub fn close(self) -> io::Result<()> {
+        self.inner.close()
+    }
+
+    /// Shut down the read, write, or both halves of this connection.
+    ///
+    /// This function will cause all pending and future I/O calls on the
+    /// specified portions to immediately return with an appropriate value
+    /// (see the documentation of `Shutdown`).
+    ///
+    /// Some platforms report `ENOTCONN` when the socket is not connected;
+    /// see `shutdown_lenient`.
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.inner.shutdown(how)
+    }
+
+    /// Shut down the read, write, or both halves of this socket, treating
+    /// `ENOTCONN` as success.
+    ///
+    /// An unconnected socket has no peer to shut down toward, so this is
+    /// useful when all that is wanted is to stop I/O on the socket
+    /// regardless of whether it was ever connected.
+    pub fn shutdown_lenient(&self, how: Shutdown) -> io::Result<()> {
+        match self.inner.shutdown(how) {
+            Err(ref e) if e.raw_os_error() == Some(libc::ENOTCONN) => Ok(()),
+            res => res,
+        }
+    }
+}
+
+impl AsRawFd for UnixDatagram {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.0
+    }
+}
+
+impl FromRawFd for UnixDatagram {
+    unsafe fn from_raw_fd(fd: RawFd) -> UnixDatagram {
+        UnixDatagram {
+            inner: Inner(fd),
+            unlink: UnlinkGuard(None),
+            local: Mutex::new(None),
+        }
+    }
+}
+
+impl IntoRawFd for UnixDatagram {
+    fn into_raw_fd(mut self) -> RawFd {
+        self.forget_unlink();
+        let fd = self.inner.0;
+        mem::forget(self);
+        fd
+    }
+}
+
+/// A Unix seqpacket socket.
+///
+/// A Unix Seqpacket socket is connection oriented but sends and receives
+/// datagrams with guaranteed ordering.
+///
+/// `UnixSeqpacket` implements `Read` and `Write`, but these preserve the
+/// message oriented semantics of the socket: each `read` receives at most one
+/// record (discarding any part of it that does not fit in the buffer) and
+/// each `write` sends exactly one record.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use unix_socket::UnixSeqpacket;
+///
+/// let path = "/path/to/my/socket";
+/// let socket = UnixSeqpacket::connect(path).unwrap();
+/// let _count = socket.send(b"hello world").unwrap();
+/// let mut buf = [0; 100];
+/// let count = socket.recv(&mut buf).unwrap();
+/// println!("socket {:?} sent {:?}", path, &buf[..count]);
+/// ```
+pub struct UnixSeqpacket {
+    inner: Inner,
+}
+
+impl fmt::Debug for UnixSeqpacket {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut builder = fmt.debug_struct("UnixSeqpacket");
+        builder.field("fd", &self.inner.0);
+        if let Ok(flags) = self.inner.fd_flags() {
+            builder.field("cloexec", &(flags & libc::FD_CLOEXEC != 0));
+        }
+        if let Ok(accepting) = self.inner.accepting() {
+            builder.field("accepting", &accepting);
+        }
+        if let Ok(addr) = self.local_addr() {
+            builder.field("local", &addr);
+        }
+        if let Ok(addr) = self.peer_addr() {
+            builder.field("peer", &addr);
+        }
+        builder.finish()
+    }
+}
+
+impl UnixSeqpacket {
+    /// Connects to the socket named by `path`.
+    ///
+    /// Linux provides, as a nonportable extension, a separate "abstract"
+    /// address namespace as opposed to filesystem-based addressing. If `path`
+    /// begins with a null byte, it will be interpreted as an "abstract"
+    /// address. Otherwise, it will be interpreted as a "pathname" address,
+    /// corresponding to a path on the filesystem.
+    ///
+    /// The socket is created with the close-on-exec flag set, so it is not
+    /// inherited by child processes.
+    pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<UnixSeqpacket> {
+        let inner = try!(Inner::new(libc::SOCK_SEQPACKET));
+        try!(inner.connect(path));
+        Ok(UnixSeqpacket { inner: inner })
+    }
+
+    /// Connects to the socket named by `path`, leaving the close-on-exec flag
+    /// unset.
+    ///
+    /// The socket will be inherited by child processes spawned after this
+    /// call. Prefer `connect` unless the descriptor is meant to be handed to
+    /// a child.
+    pub fn connect_inheritable<P: AsRef<Path>>(path: P) -> io::Result<UnixSeqpacket> {
+        let inner = try!(Inner::new_inheritable(libc::SOCK_SEQPACKET));
+        try!(inner.connect(path));
+        Ok(UnixSeqpacket { inner: inner })
+    }
+
+    /// Connects to the socket at `addr`.
+    ///
+    /// Unlike `connect`, the address namespace is chosen explicitly by the
+    /// `Address` variant rather than by a leading null byte.
+    /// `Address::Unnamed` can't be connected to and returns an error of kind
+    /// `InvalidInput`.
+    pub fn connect_address<A: Into<Address>>(addr: A) -> io::Result<UnixSeqpacket> {
+        let inner = try!(Inner::new(libc::SOCK_SEQPACKET));
+        try!(inner.connect_address(addr.into()));
+        Ok(UnixSeqpacket { inner: inner })
+    }
+
+    /// Creates a Unix seqpacket socket which is not bound or connected.
+    ///
+    /// Options may be set on the socket before connecting it with
+    /// `connect_to`.
+    pub fn unbound() -> io::Result<UnixSeqpacket> {
+        let inner = try!(Inner::new(libc::SOCK_SEQPACKET));
+        Ok(UnixSeqpacket { inner: inner })
+    }
+
+    /// Connects the socket to the socket named by `path`.
+    ///
+    /// This is the counterpart to `unbound`, allowing a socket to be
+    /// configured before it is connected.
+    pub fn connect_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.inner.connect(path)
+    }
+
+    /// Create an unnamed pair of connected sockets.
+    ///
+    /// Returns two `UnixSeqpackets`s which are connected to each other. Both have
+    /// close-on-exec set.
+    pub fn pair() -> io::Result<(UnixSeqpacket, UnixSeqpacket)> {
+        let (i1, i2) = try!(Inner::new_pair(libc::SOCK_SEQPACKET));
+        Ok((UnixSeqpacket { inner: i1 }, UnixSeqpacket { inner: i2 }))
+    }
+
+    /// Creates a new independently owned handle to the underlying socket.
+    ///
+    /// The returned `UnixListener` is a reference to the same socket that this
+    /// object references. Both handles can be used to accept incoming
+    /// connections and options set on one listener will affect the other.
+    pub fn try_clone(&self) -> io::Result<UnixSeqpacket> {
+        Ok(UnixSeqpacket { inner: try!(self.inner.try_clone()) })
+    }
+
+    /// Returns the address of this socket.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        SocketAddr::new(|addr, len| unsafe { libc::getsockname(self.inner.0, addr, len) })
+    }
+
+    /// Returns the address of this socket, or `None` if it is not bound to
+    /// one.
+    ///
+    /// Unlike `local_addr`, an unnamed address and any error are both
+    /// reported as `None`, which is convenient when displaying the address.
+    pub fn local_addr_opt(&self) -> Option<SocketAddr> {
+        match self.local_addr() {
+            Ok(ref addr) if addr.is_unnamed() => None,
+            Ok(addr) => Some(addr),
+            Err(_) => None,
+        }
+    }
+
+    /// Returns the address of this socket's peer.
+    ///
+    /// Returns the SocketAddr (path) of the peer of this connected socket
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        SocketAddr::new(|addr, len| unsafe { libc::getpeername(self.inner.0, addr, len) })
+    }
+
+    /// Returns `true` if neither end of this connection is bound to an
+    /// address.
+    ///
+    /// This is the case for sockets created by `pair`, and is intended as a
+    /// diagnostic aid. If either address can't be retrieved, `false` is
+    /// returned.
+    pub fn is_pair_end(&self) -> bool {
+        match (self.local_addr(), self.peer_addr()) {
+            (Ok(local), Ok(peer)) => local.is_unnamed() && peer.is_unnamed(),
+            _ => false,
+        }
+    }
+
+    /// Receives data from the socket from the connected peer.
+    ///
+    /// On success, returns the number of bytes read.
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.recv(buf)
+    }
+
+    /// Receives a record from the socket, distinguishing an empty record
+    /// from the end of the connection.
+    ///
+    /// `recv` returns `0` both for a zero-length record and once the peer
+    /// has shut down. This instead returns `None` once the peer has shut
+    /// down and no records remain.
+    ///
+    /// On Linux, `SO_PASSCRED` must be enabled with
+    /// `UnixSocketExt::set_passcred`, since credentials are then received
+    /// with every record, even an empty one, but not at the end of the
+    /// connection. An error of kind `InvalidInput` is returned if it is not.
+    /// The option applies to every handle to the socket, so it is left to the
+    /// caller rather than switched on and off around each call. Elsewhere,
+    /// each record is recognized by the `MSG_EOR` flag which terminates it.
+    ///
+    /// On success, returns the number of bytes read, or `None` at the end
+    /// of the connection.
+    pub fn recv_with_eof(&self, buf: &mut [u8]) -> io::Result<Option<usize>> {
+        self.inner.recv_record(buf)
+    }
+
+    /// Receives data from the socket, waiting no later than `deadline`.
+    ///
+    /// This is convenient when several operations share an overall
+    /// deadline, since the remaining time doesn't need to be recomputed for
+    /// each one. If no data arrives before `deadline`, or it has already
+    /// passed, an error of kind `TimedOut` is returned. The socket's read
+    /// timeout is not used.
+    ///
+    /// On success, returns the number of bytes read.
+    pub fn recv_deadline(&self, buf: &mut [u8], deadline: Instant) -> io::Result<usize> {
+        self.inner.recv_deadline(buf, deadline)
+    }
+
+    /// Sends data on the socket to the socket's peer.
+    ///
+    /// will return an error if the socket has not already been connected.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.send(buf)
+    }
+
+    /// Attempts to send data on the socket without blocking, for use in
+    /// hand-written futures.
+    ///
+    /// The send is made with `MSG_DONTWAIT`. If it would block,
+    /// `Poll::Pending` is returned; no wakeup is registered, so the caller's
+    /// reactor must arrange to poll again once the socket is writable.
+    /// Otherwise the result of the send is returned as `Poll::Ready`.
+    pub fn try_send_poll(&self, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.inner.try_send_poll(buf)
+    }
+
+    /// Receives a record into a series of buffers along with any file
+    /// descriptors passed by the peer.
+    ///
+    /// Received descriptors are appended to `fds`. They are owned by the
+    /// caller and have close-on-exec set.
+    ///
+    /// On success, returns the number of bytes read and whether the whole
+    /// record was received. Linux never reports `MSG_EOR` on Unix sockets,
+    /// but each receive there consumes exactly one record, so the record is
+    /// complete unless it was truncated. Elsewhere this reflects `MSG_EOR`.
+    pub fn recv_vectored_fds(&self,
+                             bufs: &mut [IoSliceMut],
+                             fds: &mut Vec<RawFd>)
+                             -> io::Result<(usize, bool)> {
+        let (msg, received) = try!(self.inner.recv_fds(bufs));
+        fds.extend(received);
+
+        let eor = if cfg!(target_os = "linux") {
+            msg.flags & libc::MSG_TRUNC == 0
+        } else {
+            msg.flags & libc::MSG_EOR != 0
+        };
+        Ok((msg.count, eor))
+    }
+
+    /// Sends a record on the socket along with file descriptors.
+    ///
+    /// The descriptors are passed to the peer with `SCM_RIGHTS` and remain
+    /// open in this process.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_fds(&self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        self.inner.send_fds(buf, fds)
+    }
+
+    /// Sets the read timeout for the socket.
+    ///
+    /// If the provided value is `None`, then `recv` and `recv_from` calls will
+    /// block indefinitely. It is an error to pass the zero `Duration` to this
+    /// method.
+    ///
+    /// A receive that times out fails with an error of kind `TimedOut`.
+    /// The `EAGAIN` reported by the operating system is translated so that
+    /// timeouts can be told apart from nonblocking receives, which fail with
+    /// `WouldBlock`.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeout(timeout, libc::SO_RCVTIMEO)
+    }
+
+    /// Sets the read timeout for the socket, treating a zero `Duration` as a
+    /// request for nonblocking mode.
+    ///
+    /// Passing `Some` of the zero `Duration` clears the read timeout and puts
+    /// the socket into nonblocking mode, so reads fail with `WouldBlock`
+    /// rather than waiting. Any other value sets the read timeout as
+    /// `set_read_timeout` does and puts the socket into blocking mode. Note
+    /// that nonblocking mode applies to writes as well as reads.
+    pub fn set_read_timeout_or_nonblocking(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_read_timeout_or_nonblocking(timeout)
+    }
+
+    /// Sets the write timeout for the socket.
+    ///
+    /// If the provided value is `None`, then `send` and `send_to` calls will
+    /// block indefinitely. It is an error to pass the zero `Duration` to this
+    /// method.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeout(timeout, libc::SO_SNDTIMEO)
+    }
+
+    /// Returns the read timeout of this socket.
+    ///
+    /// This is the value stored by the kernel rather than the one passed to
+    /// `set_read_timeout`. Timeouts are stored with at most microsecond
+    /// resolution, so any finer part is truncated, and Linux stores them as
+    /// a whole number of clock ticks, so short timeouts may be rounded up.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.inner.timeout(libc::SO_RCVTIMEO)
+    }
+
+    /// Returns the write timeout of this socket.
+    ///
+    /// This is the value stored by the kernel rather than the one passed to
+    /// `set_write_timeout`. Timeouts are stored with at most microsecond
+    /// resolution, so any finer part is truncated, and Linux stores them as
+    /// a whole number of clock ticks, so short timeouts may be rounded up.
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        self.inner.timeout(libc::SO_SNDTIMEO)
+    }
+
+    /// Sets the read and write timeouts for the socket together.
+    ///
+    /// The values are interpreted as by `set_read_timeout` and
+    /// `set_write_timeout`. If the write timeout can't be set, the read
+    /// timeout is restored to its previous value, so the socket is never
+    /// left with only one of them changed.
+    pub fn set_timeouts(&self, read: Option<Duration>, write: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeouts(read, write)
+    }
+
+    /// Returns the read and write timeouts of this socket.
+    pub fn timeouts(&self) -> io::Result<(Option<Duration>, Option<Duration>)> {
+        let read = try!(self.inner.timeout(libc::SO_RCVTIMEO));
+        let write = try!(self.inner.timeout(libc::SO_SNDTIMEO));
+        Ok((read, write))
+    }
+
+    /// Sets the size of the socket's send buffer.
+    ///
+    /// The kernel may adjust the requested size; on Linux it is doubled to
+    /// leave room for bookkeeping overhead and capped by the system
+    /// `wmem_max` limit.
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.inner.set_buffer_size(size, libc::SO_SNDBUF)
+    }
+
+    /// Returns the size of the socket's send buffer.
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.inner.buffer_size(libc::SO_SNDBUF)
+    }
+
+    /// Sets the size of the socket's receive buffer.
+    ///
+    /// The kernel may adjust the requested size; on Linux it is doubled to
+    /// leave room for bookkeeping overhead and capped by the system
+    /// `rmem_max` limit.
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.inner.set_buffer_size(size, libc::SO_RCVBUF)
+    }
+
+    /// Returns the size of the socket's receive buffer.
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        self.inner.buffer_size(libc::SO_RCVBUF)
+    }
+
+    /// Returns the size of the socket's receive buffer along with the system
+    /// limit on the size which may be requested.
+    ///
+    /// This shows whether a requested size was clamped. On Linux the limit
+    /// is `net.core.rmem_max`, and because the kernel doubles requested
+    /// sizes the buffer may be up to twice the limit. Other platforms return
+    /// an error.
+    pub fn recv_buffer_limits(&self) -> io::Result<(usize, usize)> {
+        self.inner.recv_buffer_limits()
+    }
+
+    /// Sets the value of the `SO_KEEPALIVE` option on this socket.
+    ///
+    /// Unix domain sockets have no keepalive probes, so this typically has
+    /// no effect. It is accepted for uniformity with code that configures
+    /// TCP sockets the same way.
+    pub fn set_keepalive(&self, keepalive: bool) -> io::Result<()> {
+        self.inner.setsockopt(libc::SOL_SOCKET, libc::SO_KEEPALIVE, keepalive as libc::c_int)
+    }
+
+    /// Returns the value of the `SO_KEEPALIVE` option on this socket.
+    pub fn keepalive(&self) -> io::Result<bool> {
+        self.inner.getsockopt::<libc::c_int>(libc::SOL_SOCKET, libc::SO_KEEPALIVE).map(|v| v != 0)
+    }
+
+    /// Moves the socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.inner.set_nonblocking(nonblocking)
+    }
+
+    /// Returns whether the socket is in nonblocking mode.
+    ///
+    /// This reads the descriptor's `O_NONBLOCK` flag, so it reflects the
+    /// mode however it was set, including by a constructor.
+    pub fn nonblocking(&self) -> io::Result<bool> {
+        self.inner.nonblocking()
+    }
+
+    /// Moves the socket into or out of nonblocking mode, returning whether
+    /// it was previously in nonblocking mode.
+    ///
+    /// The mode is only changed if it differs from the requested one.
+    pub fn swap_nonblocking(&self, nonblocking: bool) -> io::Result<bool> {
+        self.inner.swap_nonblocking(nonblocking)
+    }
+
+    /// Applies the options set in `cfg` to the socket.
+    ///
+    /// The options are applied in the order of `SocketConfig`'s fields. If
+    /// one can't be set, the error identifies it, and the options before it
+    /// are restored to their previous values on a best-effort basis.
+    pub fn configure(&self, cfg: &SocketConfig) -> Result<(), ConfigError> {
+        self.inner.configure(cfg)
+    }
+
+    /// Returns the value of the `SO_ERROR` option.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.inner.take_error()
+    }
+
+    /// Returns the file descriptor flags of the socket, as reported by
+    /// `fcntl(F_GETFD)`.
+    ///
+    /// This is useful for tracking down descriptor leaks, since a socket
+    /// without `FD_CLOEXEC` set will be inherited by child processes.
+    pub fn fd_flags(&self) -> io::Result<i32> {
+        self.inner.fd_flags()
+    }
+
+    /// Returns the type of the socket, as reported by `SO_TYPE`.
+    ///
+    /// This is useful for checking a socket created with `from_raw_fd`.
+    pub fn socket_type(&self) -> io::Result<SocketType> {
+        self.inner.socket_type()
+    }
+
+    /// Returns whether this handle and `other` refer to the same socket.
+    ///
+    /// Handles made with `try_clone`, or received from another process
+    /// which passed this socket, refer to the same socket, while separate
+    /// connections never do. On Linux this compares the open files with
+    /// `kcmp`; where that is unavailable, and on other platforms, the
+    /// sockets' inode numbers are compared instead.
+    pub fn same_socket(&self, other: &UnixSeqpacket) -> io::Result<bool> {
+        self.inner.same_socket(&other.inner)
+    }
+
+    /// Waits for the socket to become readable or writable.
+    ///
+    /// If `timeout` is `None`, this blocks until the socket is ready.
+    /// Otherwise it waits at most `timeout`, and the returned `Ready` will
+    /// report neither readable nor writable if it elapsed first.
+    pub fn poll_ready(&self, timeout: Option<Duration>) -> io::Result<Ready> {
+        self.inner.poll(libc::POLLIN | libc::POLLOUT, timeout)
+    }
+
+    /// Returns `true` if a read from the socket would not block.
+    pub fn readable(&self) -> io::Result<bool> {
+        self.inner.poll(libc::POLLIN, Some(Duration::from_secs(0))).map(|r| r.is_readable())
+    }
+
+    /// Returns `true` if a write to the socket would not block.
+    pub fn writable(&self) -> io::Result<bool> {
+        self.inner.poll(libc::POLLOUT, Some(Duration::from_secs(0))).map(|r| r.is_writable())
+    }
+
+    /// Closes the socket, returning any error reported by `close`.
+    ///
+    /// Dropping the socket also closes it but silently ignores errors. The
+    /// descriptor is released even if an error is returned.
+    pub fn close(self) -> io::Result<()> {
+        self.inner.close()
+    }
+
+    /// Shut down the read, write, or both halves of this connection.
+    ///
+    /// This function will cause all pending and future I/O calls on the
+    /// specified portions to immediately return with an appropriate value
+    /// (see the documentation of `Shutdown`).
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.inner.shutdown(how)
+    }
+}
+
+impl io::Read for UnixSeqpacket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::Read::read(&mut &*self, buf)
+    }
+}
+
+impl<'a> io::Read for &'a UnixSeqpacket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.recv(buf)
+    }
+}
+
+impl io::Write for UnixSeqpacket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::Write::write(&mut &*self, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::Write::flush(&mut &*self)
+    }
+}
+
+impl<'a> io::Write for &'a UnixSeqpacket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.send(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsRawFd for UnixSeqpacket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.0
+    }
+}
+
+impl FromRawFd for UnixSeqpacket {
+    unsafe fn from_raw_fd(fd: RawFd) -> UnixSeqpacket {
+        UnixSeqpacket { inner: Inner(fd) }
+    }
+}
+
+impl IntoRawFd for UnixSeqpacket {
+    fn into_raw_fd(self) -> RawFd {
+        let fd = self.inner.0;
+        mem::forget(self);
+        fd
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    extern crate tempdir;
+
+    use std::thread;
+    use std::io;
+    use std::io::prelude::*;
+    use std::time::Duration;
+    use self::tempdir::TempDir;
+    use std::net::Shutdown;
+
+    use super::*;
+
+    macro_rules! or_panic {
+        ($e:expr) => {
+            match $e {
+                Ok(e) => e,
+                Err(e) => panic!("{}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn basic_stream() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+        let msg1 = b"hello";
+        let msg2 = b"world!";
+
+        let listener = or_panic!(UnixListener::bind(&socket_path));
+        let thread = thread::spawn(move || {
+            let mut stream = or_panic!(listener.accept()).0;
+            let mut buf = [0; 5];
+            or_panic!(stream.read(&mut buf));
+            assert_eq!(&msg1[..], &buf[..]);
+            or_panic!(stream.write_all(msg2));
+        });
+
+        let mut stream = or_panic!(UnixStream::connect(&socket_path));
+        assert_eq!(Some(&*socket_path),
+                   stream.peer_addr().unwrap().as_pathname());
+        or_panic!(stream.write_all(msg1));
+        let mut buf = vec![];
+        or_panic!(stream.read_to_end(&mut buf));
+        assert_eq!(&msg2[..], &buf[..]);
+        drop(stream);
+
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn basic_seqpacket() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+        let msg1 = b"hello";
+        let msg2 = b"world!";
+
+        let listener = or_panic!(UnixSeqpacketListener::bind(&socket_path));
+        let thread = thread::spawn(move || {
+            let stream = or_panic!(listener.accept()).0;
+            let mut buf = [0; 5];
+            let res = or_panic!(stream.recv(&mut buf));
+            println!("recv in thread result was {}", res);
+            assert_eq!(&msg1[..], &buf[..]);
+            let res = or_panic!(stream.send(msg2));
+            println!("send in thread result was {}", res);
+        });
+
+        let stream = or_panic!(UnixSeqpacket::connect(&socket_path));
+        assert_eq!(Some(&*socket_path),
+                   stream.peer_addr().unwrap().as_pathname());
+        let res = or_panic!(stream.send(msg1));
+        println!("outer send result was {}", res);
+        let mut buf = vec![0,0,0,0,0,0];
+        let res = or_panic!(stream.recv(&mut buf));
+        println!("outer recv result was {}", res);
+        assert_eq!(&msg2[..], &buf[..]);
+        drop(stream);
+
+        thread.join().unwrap();
+    }
+
+
+    #[test]
+    fn pair() {
+        let msg1 = b"hello";
+        let msg2 = b"world!";
+
+        let (mut s1, mut s2) = or_panic!(UnixStream::pair());
+        let thread = thread::spawn(move || {
+            // s1 must be moved in or the test will hang!
+            let mut buf = [0; 5];
+            or_panic!(s1.read(&mut buf));
+            assert_eq!(&msg1[..], &buf[..]);
+            or_panic!(s1.write_all(msg2));
+        });
+
+        or_panic!(s2.write_all(msg1));
+        let mut buf = vec![];
+        or_panic!(s2.read_to_end(&mut buf));
+        assert_eq!(&msg2[..], &buf[..]);
+        drop(s2);
+
+        thread.join().unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn abstract_address() {
+        use os::linux::SocketAddrExt;
+
+        let socket_path = "\0the path";
+        let msg1 = b"hello";
+        let msg2 = b"world!";
+
+        let listener = or_panic!(UnixListener::bind(&socket_path));
+        let thread = thread::spawn(move || {
+            let mut stream = or_panic!(listener.accept()).0;
+            let mut buf = [0; 5];
+            or_panic!(stream.read(&mut buf));
+            assert_eq!(&msg1[..], &buf[..]);
+            or_panic!(stream.write_all(msg2));
+        });
+
+        let mut stream = or_panic!(UnixStream::connect(&socket_path));
+        assert_eq!(Some(&b"the path"[..]),
+                   stream.peer_addr().unwrap().as_abstract());
+        or_panic!(stream.write_all(msg1));
+        let mut buf = vec![];
+        or_panic!(stream.read_to_end(&mut buf));
+        assert_eq!(&msg2[..], &buf[..]);
+        drop(stream);
+
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn try_clone() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+        let msg1 = b"hello";
+        let msg2 = b"world";
+
+        let listener = or_panic!(UnixListener::bind(&socket_path));
+        let thread = thread::spawn(move || {
+            let mut stream = or_panic!(listener.accept()).0;
+            or_panic!(stream.write_all(msg1));
+            or_panic!(stream.write_all(msg2));
+        });
+
+        let mut stream = or_panic!(UnixStream::connect(&socket_path));
+        let mut stream2 = or_panic!(stream.try_clone());
+
+        let mut buf = [0; 5];
+        or_panic!(stream.read(&mut buf));
+        assert_eq!(&msg1[..], &buf[..]);
+        or_panic!(stream2.read(&mut buf));
+        assert_eq!(&msg2[..], &buf[..]);
+
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn iter() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+
+        let listener = or_panic!(UnixListener::bind(&socket_path));
+        let thread = thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = or_panic!(stream);
+                let mut buf = [0];
+                or_panic!(stream.read(&mut buf));
+            }
+        });
+
+        for _ in 0..2 {
+            let mut stream = or_panic!(UnixStream::connect(&socket_path));
+            or_panic!(stream.write_all(&[0]));
+        }
+
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn long_path() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path()
+                             .join("asdfasdfasdfasdfasdfasdfasdfasdfasdfasdfasdfasdfasdfasdfasdfa\
+                                    sasdfasdfasdasdfasdfasdfadfasdfasdfasdfasdfasdf");
+        match UnixStream::connect(&socket_path) {
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("unexpected success"),
+        }
+
+        match UnixListener::bind(&socket_path) {
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("unexpected success"),
+        }
+
+        match UnixDatagram::bind(&socket_path) {
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("unexpected success"),
+        }
+    }
+
+    #[test]
+    fn timeouts() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+
+        let _listener = or_panic!(UnixListener::bind(&socket_path));
+
+        let stream = or_panic!(UnixStream::connect(&socket_path));
+        let dur = Duration::new(15410, 0);
+
+        assert_eq!(None, or_panic!(stream.read_timeout()));
+
+        or_panic!(stream.set_read_timeout(Some(dur)));
+        assert_eq!(Some(dur), or_panic!(stream.read_timeout()));
+
+        assert_eq!(None, or_panic!(stream.write_timeout()));
+
+        or_panic!(stream.set_write_timeout(Some(dur)));
+        assert_eq!(Some(dur), or_panic!(stream.write_timeout()));
+
+        or_panic!(stream.set_read_timeout(None));
+        assert_eq!(None, or_panic!(stream.read_timeout()));
+
+        or_panic!(stream.set_write_timeout(None));
+        assert_eq!(None, or_panic!(stream.write_timeout()));
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+
+        let _listener = or_panic!(UnixListener::bind(&socket_path));
+
+        let mut stream = or_panic!(UnixStream::connect(&socket_path));
+        or_panic!(stream.set_read_timeout(Some(Duration::from_millis(1000))));
+
+        let mut buf = [0; 10];
+        let kind = stream.read(&mut buf).err().expect("expected error").kind();
+        assert!(kind == io::ErrorKind::WouldBlock || kind == io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_read_with_timeout() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+
+        let listener = or_panic!(UnixListener::bind(&socket_path));
+
+        let mut stream = or_panic!(UnixStream::connect(&socket_path));
+        or_panic!(stream.set_read_timeout(Some(Duration::from_millis(1000))));
+
+        let mut other_end = or_panic!(listener.accept()).0;
+        or_panic!(other_end.write_all(b"hello world"));
+
+        let mut buf = [0; 11];
+        or_panic!(stream.read(&mut buf));
+        assert_eq!(b"hello world", &buf[..]);
+
+        let kind = stream.read(&mut buf).err().expect("expected error").kind();
+        assert!(kind == io::ErrorKind::WouldBlock || kind == io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_unix_datagram() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path1 = dir.path().join("sock1");
+        let path2 = dir.path().join("sock2");
+
+        let sock1 = or_panic!(UnixDatagram::bind(&path1));
+        let sock2 = or_panic!(UnixDatagram::bind(&path2));
+
+        let msg = b"hello world";
+        or_panic!(sock1.send_to(msg, &path2));
+        let mut buf = [0; 11];
+        or_panic!(sock2.recv_from(&mut buf));
+        assert_eq!(msg, &buf[..]);
+    }
+
+    #[test]
+    fn test_unnamed_unix_datagram() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path1 = dir.path().join("sock1");
+
+        let sock1 = or_panic!(UnixDatagram::bind(&path1));
+        let sock2 = or_panic!(UnixDatagram::unbound());
+
+        let msg = b"hello world";
+        or_panic!(sock2.send_to(msg, &path1));
+        let mut buf = [0; 11];
+        let (usize, addr) = or_panic!(sock1.recv_from(&mut buf));
+        assert_eq!(usize, 11);
+        assert!(addr.is_unnamed());
+        assert_eq!(msg, &buf[..]);
+    }
+
+    #[test]
+    fn test_connect_unix_datagram() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path1 = dir.path().join("sock1");
+        let path2 = dir.path().join("sock2");
+
+        let bsock1 = or_panic!(UnixDatagram::bind(&path1));
+        let bsock2 = or_panic!(UnixDatagram::bind(&path2));
+        let sock = or_panic!(UnixDatagram::unbound());
+        or_panic!(sock.connect(&path1));
+
+        // Check send()
+        let msg = b"hello there";
+        or_panic!(sock.send(msg));
+        let mut buf = [0; 11];
+        let (usize, addr) = or_panic!(bsock1.recv_from(&mut buf));
+        assert_eq!(usize, 11);
+        assert!(addr.is_unnamed());
+        assert_eq!(msg, &buf[..]);
+
+        // Changing default socket works too
+        or_panic!(sock.connect(&path2));
+        or_panic!(sock.send(msg));
+        or_panic!(bsock2.recv_from(&mut buf));
+    }
+
+    #[test]
+    fn test_unix_datagram_recv() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path1 = dir.path().join("sock1");
+
+        let sock1 = or_panic!(UnixDatagram::bind(&path1));
+        let sock2 = or_panic!(UnixDatagram::unbound());
+        or_panic!(sock2.connect(&path1));
+
+        let msg = b"hello world";
+        or_panic!(sock2.send(msg));
+        let mut buf = [0; 11];
+        let size = or_panic!(sock1.recv(&mut buf));
+        assert_eq!(size, 11);
+        assert_eq!(msg, &buf[..]);
+    }
+
+    #[test]
+    fn datagram_pair() {
+        let msg1 = b"hello";
+        let msg2 = b"world!";
+
+        let (s1, s2) = or_panic!(UnixDatagram::pair());
+        let thread = thread::spawn(move || {
+            // s1 must be moved in or the test will hang!
+            let mut buf = [0; 5];
+            or_panic!(s1.recv(&mut buf));
+            assert_eq!(&msg1[..], &buf[..]);
+            or_panic!(s1.send(msg2));
+        });
+
+        or_panic!(s2.send(msg1));
+        let mut buf = [0; 6];
+        or_panic!(s2.recv(&mut buf));
+        assert_eq!(&msg2[..], &buf[..]);
+        drop(s2);
+
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn datagram_shutdown() {
+        let s1 = UnixDatagram::unbound().unwrap();
+        let s2 = s1.try_clone().unwrap();
+
+        let thread = thread::spawn(move || {
+            let mut buf = [0; 1];
+            assert_eq!(0, s1.recv_from(&mut buf).unwrap().0);
+        });
+
+        thread::sleep(Duration::from_millis(100));
+        s2.shutdown(Shutdown::Read).unwrap();;
+
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn fd_flags() {
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        unsafe {
+            or_panic!(cvt(libc::fcntl(s1.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC)));
+        }
+
+        assert!(or_panic!(s1.fd_flags()) & libc::FD_CLOEXEC != 0);
+        assert!(format!("{:?}", s1).contains("cloexec: true"));
+    }
+
+    #[test]
+    fn shutdown_after_flush() {
+        let (mut s1, mut s2) = or_panic!(UnixStream::pair());
+        let msg = vec![1; 64 * 1024];
+
+        let thread = thread::spawn(move || {
+            let mut buf = vec![];
+            or_panic!(s2.read_to_end(&mut buf));
+            buf
+        });
+
+        or_panic!(s1.write_all(&msg));
+        or_panic!(s1.shutdown_after_flush(Shutdown::Write));
+
+        let buf = thread.join().unwrap();
+        assert_eq!(&msg[..], &buf[..]);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn backlog() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+
+        let listener = or_panic!(UnixListener::bind(dir.path().join("sock")));
+        assert_eq!(LISTEN_BACKLOG as usize, or_panic!(listener.backlog()));
+
+        let listener = or_panic!(UnixSeqpacketListener::bind(dir.path().join("seqsock")));
+        assert_eq!(LISTEN_BACKLOG as usize, or_panic!(listener.backlog()));
+    }
+
+    #[test]
+    fn datagram_recv_vec() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path1 = dir.path().join("sock1");
+        let path2 = dir.path().join("sock2");
+
+        let sock1 = or_panic!(UnixDatagram::bind(&path1));
+        let sock2 = or_panic!(UnixDatagram::bind(&path2));
+
+        for &len in &[0, 11, 5000] {
+            let msg = vec![7; len];
+            or_panic!(sock1.send_to(&msg, &path2));
+            let (buf, addr) = or_panic!(sock2.recv_vec());
+            assert_eq!(msg, buf);
+            assert_eq!(Some(&*path1), addr.as_pathname());
+        }
+    }
+
+    #[test]
+    fn transient_errors() {
+        assert!(is_transient(&io::Error::from_raw_os_error(libc::ENOBUFS)));
+        assert!(is_transient(&io::Error::from_raw_os_error(libc::EAGAIN)));
+        assert!(is_transient(&io::Error::from_raw_os_error(libc::EINTR)));
+        assert!(!is_transient(&io::Error::from_raw_os_error(libc::ECONNREFUSED)));
+        assert!(!is_transient(&io::Error::new(io::ErrorKind::InvalidInput, "oops")));
+    }
+
+    #[test]
+    fn datagram_send_to_retrying() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path = dir.path().join("sock");
+
+        let receiver = or_panic!(UnixDatagram::bind(&path));
+        let sender = or_panic!(UnixDatagram::unbound());
+        or_panic!(sender.set_nonblocking(true));
+
+        // fill up the receiver's queue so that further sends fail
+        loop {
+            match sender.send_to(b"hello", &path) {
+                Ok(_) => {}
+                Err(ref e) if is_transient(e) => break,
+                Err(e) => panic!("unexpected error {}", e),
+            }
+        }
+
+        let thread = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            let mut buf = [0; 5];
+            or_panic!(receiver.recv(&mut buf));
+            receiver
+        });
+
+        assert_eq!(5, or_panic!(sender.send_to_retrying(b"hello", &path, 1000)));
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn with_sockaddr() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let listener = or_panic!(UnixListener::bind(dir.path().join("sock")));
+        let addr = or_panic!(listener.local_addr());
+
+        let family = addr.with_sockaddr(|addr, _| unsafe { (*addr).sa_family });
+        assert_eq!(libc::AF_UNIX as libc::sa_family_t, family);
+    }
+
+    // Checks the buffer size reported after `requested` was set, given the
+    // size reported by a socket left at the default. Linux doubles the
+    // requested size to allow for bookkeeping overhead.
+    fn check_buffer_size(requested: usize, actual: usize, default: usize) {
+        if cfg!(target_os = "linux") {
+            assert_eq!(requested * 2, actual);
+        } else {
+            assert!(actual != default);
+        }
+    }
+
+    #[test]
+    fn buffer_sizes() {
+        let (s1, s2) = or_panic!(UnixStream::pair());
+
+        or_panic!(s1.set_send_buffer_size(4096));
+        check_buffer_size(4096,
+                          or_panic!(s1.send_buffer_size()),
+                          or_panic!(s2.send_buffer_size()));
+
+        or_panic!(s1.set_recv_buffer_size(4096));
+        check_buffer_size(4096,
+                          or_panic!(s1.recv_buffer_size()),
+                          or_panic!(s2.recv_buffer_size()));
+    }
+
+    #[test]
+    #[ignore]
+    #[cfg(target_os = "linux")]
+    fn buffer_sizes_force() {
+        use os::linux::UnixSocketExt;
+
+        // must be run as root
+        let (s1, _s2) = or_panic!(UnixDatagram::pair());
+        let size = 64 * 1024 * 1024;
+
+        or_panic!(s1.set_send_buffer_size_force(size));
+        assert!(or_panic!(s1.send_buffer_size()) >= size);
+
+        or_panic!(s1.set_recv_buffer_size_force(size));
+        assert!(or_panic!(s1.recv_buffer_size()) >= size);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn datagram_recv_from_ancillary_credentials() {
+        use os::linux::UnixSocketExt;
+
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path1 = dir.path().join("sock1");
+        let path2 = dir.path().join("sock2");
+
+        let sock1 = or_panic!(UnixDatagram::bind(&path1));
+        let sock2 = or_panic!(UnixDatagram::bind(&path2));
+        or_panic!(sock2.set_passcred(true));
+        assert!(or_panic!(sock2.passcred()));
+
+        or_panic!(sock1.send_to(b"hello", &path2));
+        let mut buf = [0; 5];
+        let (count, addr, ancillary) = or_panic!(sock2.recv_from_ancillary(&mut buf));
+        assert_eq!(5, count);
+        assert_eq!(b"hello", &buf);
+        assert_eq!(Some(&*path1), addr.as_pathname());
+
+        let cred = ancillary.credentials().expect("missing credentials");
+        unsafe {
+            assert_eq!(libc::getpid(), cred.pid);
+            assert_eq!(libc::getuid(), cred.uid);
+            assert_eq!(libc::getgid(), cred.gid);
+        }
+        assert!(!ancillary.is_truncated());
+    }
+
+    #[test]
+    fn seqpacket_read_write() {
+        let (mut s1, s2) = or_panic!(UnixSeqpacket::pair());
+
+        or_panic!(s1.write(b"hello"));
+        or_panic!((&s1).write(b"world!"));
+
+        let reader: &mut dyn Read = &mut &s2;
+        let mut buf = [0; 10];
+        assert_eq!(5, or_panic!(reader.read(&mut buf)));
+        assert_eq!(b"hello", &buf[..5]);
+        assert_eq!(6, or_panic!(reader.read(&mut buf)));
+        assert_eq!(b"world!", &buf[..6]);
+    }
+
+    #[test]
+    fn from_fd_with_addr() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+
+        let _listener = or_panic!(UnixListener::bind(&socket_path));
+        let fd = or_panic!(UnixStream::connect(&socket_path)).into_raw_fd();
+
+        let (_stream, addr) = or_panic!(unsafe { UnixStream::from_fd_with_addr(fd) });
+        assert_eq!(Some(&*socket_path), addr.as_pathname());
+
+        let fd = or_panic!(UnixDatagram::unbound()).into_raw_fd();
+        match unsafe { UnixStream::from_fd_with_addr(fd) } {
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("unexpected success"),
+        }
+        // ownership wasn't taken, so the fd is still open
+        let sock = unsafe { UnixDatagram::from_raw_fd(fd) };
+        assert!(sock.local_addr().is_ok());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn datagram_recv_from_timestamped() {
+        use std::time::SystemTime;
+        use os::linux::UnixDatagramExt;
+
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path1 = dir.path().join("sock1");
+        let path2 = dir.path().join("sock2");
+
+        let sock1 = or_panic!(UnixDatagram::bind(&path1));
+        let sock2 = or_panic!(UnixDatagram::bind(&path2));
+        or_panic!(sock2.set_timestamping(true));
+        assert!(or_panic!(sock2.timestamping()));
+
+        or_panic!(sock1.send_to(b"hello", &path2));
+        let mut buf = [0; 5];
+        let (count, addr, time) = or_panic!(sock2.recv_from_timestamped(&mut buf));
+        assert_eq!(5, count);
+        assert_eq!(Some(&*path1), addr.as_pathname());
+
+        let now = SystemTime::now();
+        let age = now.duration_since(time).unwrap_or_else(|e| e.duration());
+        assert!(age < Duration::from_secs(5), "timestamp {:?} is far from {:?}", time, now);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn listener_debug_pending() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+
+        let listener = or_panic!(UnixListener::bind(&socket_path));
+        assert!(format!("{:?}", listener).contains("pending: 0"));
+
+        let _s1 = or_panic!(UnixStream::connect(&socket_path));
+        let _s2 = or_panic!(UnixStream::connect(&socket_path));
+        assert!(format!("{:?}", listener).contains("pending: 2"));
+    }
+
+    #[test]
+    fn close() {
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        or_panic!(s1.close());
+
+        let mut buf = [0; 1];
+        assert_eq!(0, or_panic!((&s2).read(&mut buf)));
+        or_panic!(s2.close());
+    }
+
+    #[test]
+    fn read_timeout_kind() {
+        let (mut s1, _s2) = or_panic!(UnixStream::pair());
+        or_panic!(s1.set_read_timeout(Some(Duration::from_millis(10))));
+
+        let mut buf = [0; 10];
+        let kind = s1.read(&mut buf).err().expect("expected error").kind();
+        assert_eq!(io::ErrorKind::TimedOut, kind);
+
+        let (s1, _s2) = or_panic!(UnixDatagram::pair());
+        or_panic!(s1.set_read_timeout(Some(Duration::from_millis(10))));
+        let kind = s1.recv_from(&mut buf).err().expect("expected error").kind();
+        assert_eq!(io::ErrorKind::TimedOut, kind);
+
+        // nonblocking receives are still reported as such
+        or_panic!(s1.set_nonblocking(true));
+        let kind = s1.recv(&mut buf).err().expect("expected error").kind();
+        assert_eq!(io::ErrorKind::WouldBlock, kind);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn abstract_address_os() {
+        use std::ffi::OsStr;
+        use os::linux::SocketAddrExt;
+
+        let listener = or_panic!(UnixListener::bind("\0the os path"));
+        let addr = or_panic!(listener.local_addr());
+        assert_eq!(Some(OsStr::new("the os path")), addr.as_abstract_os());
+        assert_eq!(None, addr.as_pathname());
+    }
+
+    #[test]
+    fn datagram_bind_to_reply() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path = dir.path().join("sock");
+
+        let server = or_panic!(UnixDatagram::bind(&path));
+        let client = or_panic!(UnixDatagram::bind_to_reply(&path));
+
+        or_panic!(client.send(b"ping"));
+        let mut buf = [0; 4];
+        let (count, addr) = or_panic!(server.recv_from(&mut buf));
+        assert_eq!(b"ping", &buf[..count]);
+        assert!(!addr.is_unnamed());
+
+        or_panic!(server.send_to_addr(b"pong", &addr));
+        let count = or_panic!(client.recv(&mut buf));
+        assert_eq!(b"pong", &buf[..count]);
+    }
+
+    #[test]
+    fn socket_addr_send_sync() {
+        fn _assert_send_sync<T: Send + Sync>() {}
+        _assert_send_sync::<SocketAddr>();
+    }
+
+    #[test]
+    fn datagram_recv_from_bounded() {
+        let (s1, s2) = or_panic!(UnixDatagram::pair());
+
+        or_panic!(s1.send(b"hello"));
+        let (buf, _) = or_panic!(s2.recv_from_bounded(10));
+        assert_eq!(b"hello", &buf[..]);
+
+        or_panic!(s1.send(&[0; 20]));
+        match s2.recv_from_bounded(10) {
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData => {
+                if cfg!(target_os = "linux") {
+                    assert!(e.to_string().contains("20 bytes"), "{}", e);
+                }
+            }
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("unexpected success"),
+        }
+    }
+
+    #[test]
+    fn datagram_send_retries_eintr() {
+        use std::sync::atomic::AtomicBool;
+
+        static SIGNALS: AtomicUsize = AtomicUsize::new(0);
+
+        extern "C" fn handler(_: libc::c_int) {
+            SIGNALS.fetch_add(1, atomic::Ordering::SeqCst);
+        }
+
+        let mut old: libc::sigaction = unsafe { mem::zeroed() };
+        unsafe {
+            // no SA_RESTART, so the blocked send fails with EINTR
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction = handler as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            or_panic!(cvt(libc::sigaction(libc::SIGUSR1, &action, &mut old)));
+        }
+
+        let (s1, s2) = or_panic!(UnixDatagram::pair());
+        or_panic!(s1.set_nonblocking(true));
+        loop {
+            match s1.send(b"hello") {
+                Ok(_) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => panic!("unexpected error {}", e),
+            }
+        }
+        or_panic!(s1.set_nonblocking(false));
+
+        let sent = Arc::new(AtomicBool::new(false));
+        let sender = unsafe { libc::pthread_self() };
+        let thread = {
+            let sent = sent.clone();
+            thread::spawn(move || {
+                // keep interrupting the sender until its send completes, only
+                // making room for it once it has been signalled many times
+                let mut signals = 0;
+                while !sent.load(atomic::Ordering::SeqCst) {
+                    unsafe {
+                        libc::pthread_kill(sender, libc::SIGUSR1);
+                    }
+                    signals += 1;
+                    if signals == 20 {
+                        // the sender is only woken once most of its buffer
+                        // is free
+                        or_panic!(s2.set_nonblocking(true));
+                        let mut buf = [0; 5];
+                        while s2.recv(&mut buf).is_ok() {}
+                    }
+                    thread::sleep(Duration::from_millis(1));
+                }
+                s2
+            })
+        };
+
+        let res = s1.send(b"hello");
+        sent.store(true, atomic::Ordering::SeqCst);
+        thread.join().unwrap();
+        unsafe {
+            or_panic!(cvt(libc::sigaction(libc::SIGUSR1, &old, ptr::null_mut())));
+        }
+
+        assert_eq!(5, or_panic!(res));
+        assert!(SIGNALS.load(atomic::Ordering::SeqCst) > 0);
+    }
+
+    #[test]
+    fn unlink_on_drop() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+
+        let mut listener = or_panic!(UnixListener::bind(&socket_path));
+        or_panic!(listener.set_unlink_on_drop(true));
+        drop(listener);
+        assert!(!socket_path.exists());
+
+        let mut listener = or_panic!(UnixListener::bind(&socket_path));
+        or_panic!(listener.set_unlink_on_drop(true));
+        listener.forget_unlink();
+        drop(listener);
+        assert!(socket_path.exists());
+    }
+
+    #[test]
+    fn accept_raw() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+
+        let listener = or_panic!(UnixListener::bind(&socket_path));
+        or_panic!(listener.set_nonblocking(true));
+        assert!(or_panic!(listener.accept_raw()).is_none());
+
+        let mut client = or_panic!(UnixStream::connect(&socket_path));
+        let (fd, addr) = or_panic!(listener.accept_raw()).expect("no connection");
+        assert!(addr.is_unnamed());
+
+        let mut stream = unsafe { UnixStream::from_raw_fd(fd) };
+        assert!(or_panic!(stream.fd_flags()) & libc::FD_CLOEXEC != 0);
+        let mut buf = [0; 5];
+        let kind = stream.read(&mut buf).err().expect("expected error").kind();
+        assert_eq!(io::ErrorKind::WouldBlock, kind);
+
+        or_panic!(client.write_all(b"hello"));
+        or_panic!(stream.read_exact(&mut buf));
+        assert_eq!(b"hello", &buf);
+    }
+
+    #[test]
+    fn recv_exact() {
+        let (mut s1, s2) = or_panic!(UnixStream::pair());
+
+        let thread = thread::spawn(move || {
+            or_panic!(s1.write_all(b"hello "));
+            thread::sleep(Duration::from_millis(50));
+            or_panic!(s1.write_all(b"world"));
+        });
+
+        let mut buf = [0; 11];
+        assert_eq!(11, or_panic!(s2.recv_exact(&mut buf)));
+        assert_eq!(b"hello world", &buf);
+
+        thread.join().unwrap();
+        assert_eq!(0, or_panic!(s2.recv_exact(&mut buf)));
+    }
+
+    #[test]
+    fn accept_template() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+        let timeout = Duration::from_secs(10);
+
+        let mut listener = or_panic!(UnixListener::bind(&socket_path));
+        listener.set_accept_template(SocketConfig {
+            read_timeout: Some(timeout),
+            ..SocketConfig::default()
+        });
+
+        let _client = or_panic!(UnixStream::connect(&socket_path));
+        let (stream, _) = or_panic!(listener.accept());
+        assert_eq!(Some(timeout), or_panic!(stream.read_timeout()));
+        assert_eq!(None, or_panic!(stream.write_timeout()));
+    }
+
+    #[test]
+    fn error_predicates() {
+        let broken_pipe = io::Error::from_raw_os_error(libc::EPIPE);
+        let reset = io::Error::from_raw_os_error(libc::ECONNRESET);
+        let would_block = io::Error::from_raw_os_error(libc::EWOULDBLOCK);
+        let not_connected = io::Error::from_raw_os_error(libc::ENOTCONN);
+        let other = io::Error::new(io::ErrorKind::InvalidInput, "oops");
+
+        assert!(is_broken_pipe(&broken_pipe));
+        assert!(!is_broken_pipe(&reset));
+        assert!(!is_broken_pipe(&other));
+
+        assert!(is_connection_reset(&reset));
+        assert!(!is_connection_reset(&broken_pipe));
+        assert!(!is_connection_reset(&other));
+
+        assert!(is_would_block(&would_block));
+        assert!(is_would_block(&io::Error::from_raw_os_error(libc::EAGAIN)));
+        assert!(!is_would_block(&not_connected));
+        assert!(!is_would_block(&other));
+
+        assert!(is_not_connected(&not_connected));
+        assert!(!is_not_connected(&would_block));
+        assert!(!is_not_connected(&other));
+    }
+
+    #[test]
+    fn datagram_drain() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path1 = dir.path().join("sock1");
+        let path2 = dir.path().join("sock2");
+
+        let sock1 = or_panic!(UnixDatagram::bind(&path1));
+        let sock2 = or_panic!(UnixDatagram::bind(&path2));
+
+        let msgs: [&[u8]; 3] = [b"one", b"two", &[3; 4096]];
+        for msg in &msgs {
+            or_panic!(sock1.send_to(msg, &path2));
         }
-        builder.finish()
+
+        let mut received = vec![];
+        let count = or_panic!(sock2.drain(|buf, addr| {
+            assert_eq!(Some(&*path1), addr.as_pathname());
+            received.push(buf.to_vec());
+        }));
+        assert_eq!(3, count);
+        assert_eq!(&msgs[..], &received.iter().map(|v| &v[..]).collect::<Vec<_>>()[..]);
+
+        assert_eq!(0, or_panic!(sock2.drain(|_, _| panic!("unexpected datagram"))));
     }
-}
 
-impl UnixListener {
-    /// Creates a new `UnixListener` bound to the specified socket.
-    ///
-    /// Linux provides, as a nonportable extension, a separate "abstract"
-    /// address namespace as opposed to filesystem-based addressing. If `path`
-    /// begins with a null byte, it will be interpreted as an "abstract"
-    /// address. Otherwise, it will be interpreted as a "pathname" address,
-    /// corresponding to a path on the filesystem.
-    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<UnixListener> {
-        unsafe {
-            let inner = try!(Inner::new(libc::SOCK_STREAM));
-            let (addr, len) = try!(sockaddr_un(path));
+    #[test]
+    fn connect_cloexec() {
+        use std::process::Command;
 
-            try!(cvt(libc::bind(inner.0, &addr as *const _ as *const _, len)));
-            try!(cvt(libc::listen(inner.0, 128)));
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+        let listener = or_panic!(UnixListener::bind(&socket_path));
+
+        let inherited = |fd: RawFd| {
+            or_panic!(Command::new("sh").arg("-c").arg(format!("[ -e /dev/fd/{} ]", fd)).status())
+                .success()
+        };
+
+        let stream = or_panic!(UnixStream::connect(&socket_path));
+        assert!(or_panic!(stream.fd_flags()) & libc::FD_CLOEXEC != 0);
+        assert!(!inherited(stream.as_raw_fd()));
+
+        let stream = or_panic!(UnixStream::connect_inheritable(&socket_path));
+        assert!(or_panic!(stream.fd_flags()) & libc::FD_CLOEXEC == 0);
+        assert!(inherited(stream.as_raw_fd()));
+
+        let (accepted, _) = or_panic!(listener.accept());
+        assert!(or_panic!(accepted.fd_flags()) & libc::FD_CLOEXEC != 0);
+        assert!(!inherited(accepted.as_raw_fd()));
+
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        assert!(or_panic!(s1.fd_flags()) & libc::FD_CLOEXEC != 0);
+        assert!(or_panic!(s2.fd_flags()) & libc::FD_CLOEXEC != 0);
+        assert!(!inherited(s1.as_raw_fd()));
+    }
+
+    #[test]
+    fn bind_or_replace() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+
+        // Leaves the socket file behind with nothing listening on it
+        drop(or_panic!(UnixListener::bind(&socket_path)));
+        match UnixListener::bind(&socket_path) {
+            Err(ref e) if e.kind() == io::ErrorKind::AddrInUse => {}
+            other => panic!("unexpected result {:?}", other),
+        }
 
-            Ok(UnixListener { inner: inner })
+        let listener = or_panic!(UnixListener::bind_or_replace(&socket_path));
+        or_panic!(UnixStream::connect(&socket_path));
+        or_panic!(listener.accept());
+
+        match UnixListener::bind_or_replace(&socket_path) {
+            Err(ref e) if e.kind() == io::ErrorKind::AddrInUse => {}
+            other => panic!("unexpected result {:?}", other),
         }
     }
 
-    /// Accepts a new incoming connection to this listener.
-    ///
-    /// This function will block the calling thread until a new Unix connection
-    /// is established. When established, the corersponding `UnixStream` and
-    /// the remote peer's address will be returned.
-    pub fn accept(&self) -> io::Result<(UnixStream, SocketAddr)> {
-        unsafe {
-            let mut fd = 0;
-            let addr = try!(SocketAddr::new(|addr, len| {
-                fd = libc::accept(self.inner.0, addr, len);
-                fd
-            }));
+    #[test]
+    fn bind_or_replace_regular_file() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let file_path = dir.path().join("file");
+        or_panic!(fs::write(&file_path, b"data"));
 
-            Ok((UnixStream { inner: Inner(fd) }, addr))
+        match UnixListener::bind_or_replace(&file_path) {
+            Err(ref e) if e.kind() == io::ErrorKind::AddrInUse => {}
+            other => panic!("unexpected result {:?}", other),
         }
+        assert_eq!(or_panic!(fs::read(&file_path)), b"data");
     }
 
-    /// Creates a new independently owned handle to the underlying socket.
-    ///
-    /// The returned `UnixListener` is a reference to the same socket that this
-    /// object references. Both handles can be used to accept incoming
-    /// connections and options set on one listener will affect the other.
-    pub fn try_clone(&self) -> io::Result<UnixListener> {
-        Ok(UnixListener { inner: try!(self.inner.try_clone()) })
+    #[test]
+    fn ancillary_other_in_order() {
+        let header_len = unsafe { libc::CMSG_LEN(0) as usize };
+        let fd_space = unsafe { libc::CMSG_SPACE(mem::size_of::<RawFd>() as _) as usize };
+        let other_space = unsafe { libc::CMSG_SPACE(3) as usize };
+        let mut control = vec![0u8; fd_space + other_space];
+
+        unsafe {
+            let mut cmsg: libc::cmsghdr = mem::zeroed();
+            cmsg.cmsg_len = libc::CMSG_LEN(mem::size_of::<RawFd>() as _) as _;
+            cmsg.cmsg_level = libc::SOL_SOCKET;
+            cmsg.cmsg_type = libc::SCM_RIGHTS;
+            ptr::write_unaligned(control.as_mut_ptr() as *mut libc::cmsghdr, cmsg);
+            ptr::write_unaligned(control[header_len..].as_mut_ptr() as *mut RawFd, 7);
+
+            cmsg.cmsg_len = libc::CMSG_LEN(3) as _;
+            cmsg.cmsg_level = 1234;
+            cmsg.cmsg_type = 5;
+            ptr::write_unaligned(control[fd_space..].as_mut_ptr() as *mut libc::cmsghdr, cmsg);
+        }
+        control[fd_space + header_len..fd_space + header_len + 3].copy_from_slice(b"abc");
+
+        let ancillary = Ancillary::parse(&control, 0);
+        let messages = ancillary.messages().cloned().collect::<Vec<_>>();
+        assert_eq!(vec![ControlMessage::Rights(vec![7]),
+                        ControlMessage::Other {
+                            level: 1234,
+                            cmsg_type: 5,
+                            data: b"abc".to_vec(),
+                        }],
+                   messages);
     }
 
-    /// Returns the local socket address of this listener.
-    pub fn local_addr(&self) -> io::Result<SocketAddr> {
-        SocketAddr::new(|addr, len| unsafe { libc::getsockname(self.inner.0, addr, len) })
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn socket_domain() {
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        assert_eq!(libc::AF_UNIX, or_panic!(domain(s1.as_raw_fd())));
+
+        let udp = or_panic!(::std::net::UdpSocket::bind("127.0.0.1:0"));
+        assert_eq!(libc::AF_INET, or_panic!(domain(udp.as_raw_fd())));
     }
 
-    /// Moves the socket into or out of nonblocking mode.
-    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
-        self.inner.set_nonblocking(nonblocking)
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn stream_drain() {
+        let (mut s1, mut s2) = or_panic!(UnixStream::pair());
+
+        or_panic!(s1.write_all(&[0; 1000]));
+        assert!(or_panic!(s1.drain(Some(Duration::from_millis(10)))) > 0);
+
+        let mut buf = [0; 1000];
+        or_panic!(s2.read_exact(&mut buf));
+        assert_eq!(0, or_panic!(s1.drain(None)));
     }
 
-    /// Returns the value of the `SO_ERROR` option.
-    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
-        self.inner.take_error()
+    #[test]
+    fn datagram_recv_with_control() {
+        let (s1, s2) = or_panic!(UnixDatagram::pair());
+        let mut control = [0u8; 64];
+        let mut buf = [0u8; 1];
+
+        for _ in 0..3 {
+            or_panic!(s1.send_fds(b"x", &[s1.as_raw_fd()]));
+
+            let (len, control_len, _) = or_panic!(s2.recv_with_control(&mut buf, &mut control));
+            assert_eq!(1, len);
+            let fds = Ancillary::from_control(&control[..control_len]).fds();
+            assert_eq!(1, fds.len());
+            assert!(fds[0] != s1.as_raw_fd());
+
+            // The received descriptor refers to s1, so it reaches s2
+            let received = unsafe { UnixDatagram::from_raw_fd(fds[0]) };
+            assert!(or_panic!(received.fd_flags()) & libc::FD_CLOEXEC != 0);
+            or_panic!(received.send(b"x"));
+            or_panic!(s2.recv(&mut buf));
+            assert_eq!(b"x", &buf);
+        }
     }
 
-    /// Returns an iterator over incoming connections.
-    ///
-    /// The iterator will never return `None` and will also not yield the
-    /// peer's `SocketAddr` structure.
-    pub fn incoming<'a>(&'a self) -> IncomingStream<'a> {
-        IncomingStream { listener: self }
+    #[test]
+    fn readiness() {
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        assert!(or_panic!(s1.writable()));
+        assert!(!or_panic!(s1.readable()));
+
+        let ready = or_panic!(s1.poll_ready(Some(Duration::from_millis(10))));
+        assert!(ready.is_writable());
+        assert!(!ready.is_readable());
+
+        or_panic!((&s2).write_all(b"hello"));
+        assert!(or_panic!(s1.readable()));
+        assert!(or_panic!(s1.poll_ready(None)).is_readable());
     }
-}
 
-impl AsRawFd for UnixListener {
-    fn as_raw_fd(&self) -> RawFd {
-        self.inner.0
+    #[test]
+    fn datagram_shutdown_lenient() {
+        let sock = or_panic!(UnixDatagram::unbound());
+        or_panic!(sock.shutdown_lenient(Shutdown::Both));
+
+        let (s1, _s2) = or_panic!(UnixDatagram::pair());
+        or_panic!(s1.shutdown_lenient(Shutdown::Write));
+        assert!(s1.send(b"hello").is_err());
     }
-}
 
-impl FromRawFd for UnixListener {
-    unsafe fn from_raw_fd(fd: RawFd) -> UnixListener {
-        UnixListener { inner: Inner(fd) }
+    #[test]
+    fn recv_fds_cloexec() {
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        let (d1, _d2) = or_panic!(UnixDatagram::pair());
+
+        or_panic!(s1.send_fds(b"x", &[d1.as_raw_fd()]));
+        let mut buf = [0; 1];
+        let (len, fds) = or_panic!(s2.recv_fds(&mut buf));
+        assert_eq!(1, len);
+        assert_eq!(1, fds.len());
+
+        let received = unsafe { UnixDatagram::from_raw_fd(fds[0]) };
+        assert!(or_panic!(received.fd_flags()) & libc::FD_CLOEXEC != 0);
     }
-}
 
-impl IntoRawFd for UnixListener {
-    fn into_raw_fd(self) -> RawFd {
-        let fd = self.inner.0;
-        mem::forget(self);
-        fd
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn listening() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+
+        let listener = or_panic!(UnixListener::bind(&socket_path));
+        let stream = or_panic!(UnixStream::connect(&socket_path));
+
+        assert!(or_panic!(is_listening(listener.as_raw_fd())));
+        assert!(!or_panic!(is_listening(stream.as_raw_fd())));
     }
-}
 
-impl<'a> IntoIterator for &'a UnixListener {
-    type Item = io::Result<UnixStream>;
-    type IntoIter = IncomingStream<'a>;
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn abstract_address_padded_len() {
+        let addr = {
+            let listener = or_panic!(UnixListener::bind("\0padded len"));
+            or_panic!(listener.local_addr())
+        };
+        let padded = mem::size_of::<libc::sockaddr_un>();
 
-    fn into_iter(self) -> IncomingStream<'a> {
-        self.incoming()
+        let listener = or_panic!(UnixListener::bind_addr_with_len(&addr, padded));
+        match UnixStream::connect_addr_with_len(&addr, addr.len as usize) {
+            Err(ref e) if e.kind() == io::ErrorKind::ConnectionRefused => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        or_panic!(UnixStream::connect_addr_with_len(&addr, padded));
+        or_panic!(listener.accept());
+
+        assert!(UnixStream::connect_addr_with_len(&addr, padded + 1).is_err());
     }
-}
 
-/// An iterator over incoming connections to a `UnixListener`.
-///
-/// It will never return `None`.
-#[derive(Debug)]
-pub struct IncomingStream<'a> {
-    listener: &'a UnixListener,
-}
+    #[test]
+    fn readiness_hup() {
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        let ready = or_panic!(s1.poll_ready(Some(Duration::from_secs(0))));
+        assert!(!ready.is_hup());
+        assert!(!ready.is_error());
+        assert!(!ready.is_invalid());
 
-impl<'a> Iterator for IncomingStream<'a> {
-    type Item = io::Result<UnixStream>;
+        drop(s2);
+        let ready = or_panic!(s1.poll_ready(Some(Duration::from_secs(0))));
+        assert!(ready.is_hup());
+        assert!(!ready.is_invalid());
+    }
 
-    fn next(&mut self) -> Option<io::Result<UnixStream>> {
-        Some(self.listener.accept().map(|s| s.0))
+    #[test]
+    fn socket_type() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+
+        let listener = or_panic!(UnixListener::bind(dir.path().join("stream")));
+        assert_eq!(SocketType::Stream, or_panic!(listener.socket_type()));
+        let (stream, _) = or_panic!(UnixStream::pair());
+        assert_eq!(SocketType::Stream, or_panic!(stream.socket_type()));
+
+        let (datagram, _) = or_panic!(UnixDatagram::pair());
+        assert_eq!(SocketType::Datagram, or_panic!(datagram.socket_type()));
+
+        let listener = or_panic!(UnixSeqpacketListener::bind(dir.path().join("seqpacket")));
+        assert_eq!(SocketType::Seqpacket, or_panic!(listener.socket_type()));
+        let (seqpacket, _) = or_panic!(UnixSeqpacket::pair());
+        assert_eq!(SocketType::Seqpacket, or_panic!(seqpacket.socket_type()));
+
+        // The type comes from the kernel rather than the wrapper
+        let stream = unsafe { UnixStream::from_raw_fd(datagram.into_raw_fd()) };
+        assert_eq!(SocketType::Datagram, or_panic!(stream.socket_type()));
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        (usize::max_value(), None)
+    #[test]
+    fn seqpacket_recv_vectored_fds() {
+        let (s1, s2) = or_panic!(UnixSeqpacket::pair());
+        let (d1, _d2) = or_panic!(UnixDatagram::pair());
+
+        or_panic!(s1.send_fds(b"hello world", &[d1.as_raw_fd()]));
+        let mut buf1 = [0; 6];
+        let mut buf2 = [0; 5];
+        let mut fds = vec![];
+        let (len, eor) = {
+            let mut bufs = [IoSliceMut::new(&mut buf1), IoSliceMut::new(&mut buf2)];
+            or_panic!(s2.recv_vectored_fds(&mut bufs, &mut fds))
+        };
+        assert_eq!(11, len);
+        assert!(eor);
+        assert_eq!(b"hello ", &buf1);
+        assert_eq!(b"world", &buf2);
+        assert_eq!(1, fds.len());
+        let received = unsafe { UnixDatagram::from_raw_fd(fds[0]) };
+        assert!(or_panic!(received.fd_flags()) & libc::FD_CLOEXEC != 0);
+
+        or_panic!(s1.send(b"hello world"));
+        let mut fds = vec![];
+        let (len, eor) = or_panic!(s2.recv_vectored_fds(&mut [IoSliceMut::new(&mut buf2)],
+                                                        &mut fds));
+        assert_eq!(5, len);
+        assert!(!eor);
+        assert!(fds.is_empty());
     }
-}
 
-/// A Unix datagram socket.
-///
-/// # Examples
-///
-/// ```rust,no_run
-/// use unix_socket::UnixDatagram;
-///
-/// let socket = UnixDatagram::bind("/path/to/my/socket").unwrap();
-/// socket.send_to(b"hello world", "/path/to/other/socket").unwrap();
-/// let mut buf = [0; 100];
-/// let (count, address) = socket.recv_from(&mut buf).unwrap();
-/// println!("socket {:?} sent {:?}", address, &buf[..count]);
-/// ```
-pub struct UnixDatagram {
-    inner: Inner,
-}
+    #[test]
+    fn read_timeout_or_nonblocking() {
+        let (mut s1, _s2) = or_panic!(UnixStream::pair());
 
-impl fmt::Debug for UnixDatagram {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let mut builder = fmt.debug_struct("UnixDatagram");
-        builder.field("fd", &self.inner.0);
-        if let Ok(addr) = self.local_addr() {
-            builder.field("local", &addr);
-        }
-        if let Ok(addr) = self.peer_addr() {
-            builder.field("peer", &addr);
-        }
-        builder.finish()
+        or_panic!(s1.set_read_timeout_or_nonblocking(Some(Duration::from_secs(0))));
+        assert_eq!(None, or_panic!(s1.read_timeout()));
+        let mut buf = [0; 10];
+        let kind = s1.read(&mut buf).err().expect("expected error").kind();
+        assert_eq!(io::ErrorKind::WouldBlock, kind);
+
+        or_panic!(s1.set_read_timeout_or_nonblocking(Some(Duration::from_secs(1))));
+        assert_eq!(Some(Duration::from_secs(1)), or_panic!(s1.read_timeout()));
+        assert!(!or_panic!(s1.inner.nonblocking()));
+
+        or_panic!(s1.set_read_timeout_or_nonblocking(None));
+        assert_eq!(None, or_panic!(s1.read_timeout()));
     }
-}
 
-impl UnixDatagram {
-    /// Creates a Unix datagram socket bound to the given path.
-    ///
-    /// Linux provides, as a nonportable extension, a separate "abstract"
-    /// address namespace as opposed to filesystem-based addressing. If `path`
-    /// begins with a null byte, it will be interpreted as an "abstract"
-    /// address. Otherwise, it will be interpreted as a "pathname" address,
-    /// corresponding to a path on the filesystem.
-    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<UnixDatagram> {
-        unsafe {
-            let inner = try!(Inner::new(libc::SOCK_DGRAM));
-            let (addr, len) = try!(sockaddr_un(path));
+    #[test]
+    fn configured_pair() {
+        let cfg = SocketConfig {
+            read_timeout: Some(Duration::from_secs(1)),
+            ..SocketConfig::default()
+        };
+        let (s1, s2) = or_panic!(UnixStream::configured_pair(&cfg));
+        assert_eq!(Some(Duration::from_secs(1)), or_panic!(s1.read_timeout()));
+        assert_eq!(Some(Duration::from_secs(1)), or_panic!(s2.read_timeout()));
+        assert_eq!(None, or_panic!(s1.write_timeout()));
+    }
+
+    #[test]
+    fn datagram_notify() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path1 = dir.path().join("sock1");
+        let path2 = dir.path().join("sock2");
+
+        let sock1 = or_panic!(UnixDatagram::bind(&path1));
+        let sock2 = or_panic!(UnixDatagram::bind(&path2));
+        or_panic!(sock1.connect(&path2));
+
+        or_panic!(sock1.notify());
+        let mut buf = [0; 10];
+        let (len, addr) = or_panic!(sock2.recv_from(&mut buf));
+        assert_eq!(0, len);
+        assert_eq!(Some(&*path1), addr.as_pathname());
+    }
 
-            try!(cvt(libc::bind(inner.0, &addr as *const _ as *const _, len)));
+    #[test]
+    fn abstract_name() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let listener = or_panic!(UnixListener::bind(dir.path().join("sock")));
+        assert_eq!(None, or_panic!(listener.local_addr()).abstract_name());
 
-            Ok(UnixDatagram { inner: inner })
-        }
+        let sock = or_panic!(UnixDatagram::unbound());
+        assert_eq!(None, or_panic!(sock.local_addr()).abstract_name());
     }
 
-    /// Creates a Unix Datagram socket which is not bound to any address.
-    pub fn unbound() -> io::Result<UnixDatagram> {
-        let inner = try!(Inner::new(libc::SOCK_DGRAM));
-        Ok(UnixDatagram { inner: inner })
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn abstract_name_linux() {
+        use os::linux::SocketAddrExt;
+
+        let listener = or_panic!(UnixListener::bind("\0abstract name"));
+        let addr = or_panic!(listener.local_addr());
+        assert_eq!(Some(&b"abstract name"[..]), addr.abstract_name());
+        assert_eq!(addr.abstract_name(), addr.as_abstract());
     }
 
-    /// Create an unnamed pair of connected sockets.
-    ///
-    /// Returns two `UnixDatagrams`s which are connected to each other.
-    pub fn pair() -> io::Result<(UnixDatagram, UnixDatagram)> {
-        let (i1, i2) = try!(Inner::new_pair(libc::SOCK_DGRAM));
-        Ok((UnixDatagram { inner: i1 }, UnixDatagram { inner: i2 }))
+    #[test]
+    fn seqpacket_unbound_connect_to() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+        let listener = or_panic!(UnixSeqpacketListener::bind(&socket_path));
+
+        let client = or_panic!(UnixSeqpacket::unbound());
+        or_panic!(client.set_recv_buffer_size(4096));
+        or_panic!(client.connect_to(&socket_path));
+        let (server, _) = or_panic!(listener.accept());
+
+        or_panic!(server.send(b"hello"));
+        let mut buf = [0; 10];
+        assert_eq!(5, or_panic!(client.recv(&mut buf)));
+        assert_eq!(b"hello", &buf[..5]);
+        let default = or_panic!(UnixSeqpacket::unbound());
+        check_buffer_size(4096,
+                          or_panic!(client.recv_buffer_size()),
+                          or_panic!(default.recv_buffer_size()));
     }
 
-    /// Connects the socket to the specified address.
-    ///
-    /// The `send` method may be used to send data to the specified address.
-    /// `recv` and `recv_from` will only receive data from that address.
-    pub fn connect<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        unsafe {
-            let (addr, len) = try!(sockaddr_un(path));
+    #[test]
+    fn max_path_len_bind() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let dir_len = dir.path().as_os_str().len() + 1;
+        assert!(dir_len < max_path_len());
 
-            try!(cvt(libc::connect(self.inner.0, &addr as *const _ as *const _, len)));
+        let name = "s".repeat(max_path_len() - dir_len);
+        let path = dir.path().join(&name);
+        assert_eq!(max_path_len(), path.as_os_str().len());
+        or_panic!(UnixListener::bind(&path));
 
-            Ok(())
+        let path = dir.path().join(name + "s");
+        match UnixListener::bind(&path) {
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
+            other => panic!("unexpected result {:?}", other),
         }
     }
 
-    /// Creates a new independently owned handle to the underlying socket.
-    ///
-    /// The returned `UnixListener` is a reference to the same socket that this
-    /// object references. Both handles can be used to accept incoming
-    /// connections and options set on one listener will affect the other.
-    pub fn try_clone(&self) -> io::Result<UnixDatagram> {
-        Ok(UnixDatagram { inner: try!(self.inner.try_clone()) })
-    }
+    #[test]
+    fn connect_any() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let missing = dir.path().join("missing");
+        let stale = dir.path().join("stale");
+        let live = dir.path().join("live");
 
-    /// Returns the address of this socket.
-    pub fn local_addr(&self) -> io::Result<SocketAddr> {
-        SocketAddr::new(|addr, len| unsafe { libc::getsockname(self.inner.0, addr, len) })
-    }
+        drop(or_panic!(UnixListener::bind(&stale)));
+        let listener = or_panic!(UnixListener::bind(&live));
 
-    /// Returns the address of this socket's peer.
-    ///
-    /// The `connect` method will connect the socket to a peer.
-    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
-        SocketAddr::new(|addr, len| unsafe { libc::getpeername(self.inner.0, addr, len) })
+        let stream = or_panic!(UnixStream::connect_any(&[&missing, &stale, &live]));
+        assert_eq!(Some(&*live), or_panic!(stream.peer_addr()).as_pathname());
+        or_panic!(listener.accept());
+
+        or_panic!(UnixStream::connect_any(&[&live, &missing]));
+        match UnixStream::connect_any(&[&stale, &missing]) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            other => panic!("unexpected result {:?}", other),
+        }
     }
 
-    /// Receives data from the socket.
-    ///
-    /// On success, returns the number of bytes read and the address from
-    /// whence the data came.
-    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
-        let mut count = 0;
-        let addr = try!(SocketAddr::new(|addr, len| {
-            unsafe {
-                count = libc::recvfrom(self.inner.0,
-                                       buf.as_mut_ptr() as *mut _,
-                                       buf.len(),
-                                       0,
-                                       addr,
-                                       len);
-                if count > 0 {
-                    1
-                } else if count == 0 {
-                    0
-                } else {
-                    -1
-                }
-            }
-        }));
+    #[test]
+    fn recv_into_two() {
+        let (mut s1, s2) = or_panic!(UnixStream::pair());
+        or_panic!(s1.write_all(b"hello world"));
+
+        let mut ring = [0; 8];
+        let count = {
+            let (head, tail) = ring.split_at_mut(4);
+            or_panic!(s2.recv_into_two(tail, head))
+        };
+        assert_eq!(8, count);
+        assert_eq!(b"o wohell", &ring);
 
-        Ok((count as usize, addr))
+        let mut buf1 = [0; 1];
+        let mut buf2 = [0; 5];
+        let count = or_panic!(s2.recv_vectored(&mut [IoSliceMut::new(&mut buf1),
+                                                     IoSliceMut::new(&mut buf2)]));
+        assert_eq!(3, count);
+        assert_eq!(b"r", &buf1);
+        assert_eq!(b"ld", &buf2[..2]);
     }
 
-    /// Receives data from the socket.
-    ///
-    /// On success, returns the number of bytes read.
-    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner.recv(buf)
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn priority() {
+        use os::linux::UnixSocketExt;
+
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        assert_eq!(0, or_panic!(s1.priority()));
+        or_panic!(s1.set_priority(3));
+        assert_eq!(3, or_panic!(s1.priority()));
+
+        let (d1, _d2) = or_panic!(UnixDatagram::pair());
+        or_panic!(d1.set_priority(1));
+        assert_eq!(1, or_panic!(d1.priority()));
     }
 
-    /// Sends data on the socket to the specified address.
-    ///
-    /// On success, returns the number of bytes written.
-    pub fn send_to<P: AsRef<Path>>(&self, buf: &[u8], path: P) -> io::Result<usize> {
-        unsafe {
-            let (addr, len) = try!(sockaddr_un(path));
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn set_backlog() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+        let listener = or_panic!(UnixListener::bind(&socket_path));
 
-            let count = try!(cvt_s(libc::sendto(self.inner.0,
-                                                buf.as_ptr() as *const _,
-                                                buf.len(),
-                                                0,
-                                                &addr as *const _ as *const _,
-                                                len)));
-            Ok(count as usize)
+        let connect = || {
+            let inner = or_panic!(Inner::new(libc::SOCK_STREAM));
+            or_panic!(inner.set_nonblocking(true));
+            inner.connect(&socket_path).map(|_| inner)
+        };
+
+        or_panic!(listener.set_backlog(1));
+        assert_eq!(1, or_panic!(listener.backlog()));
+        // Linux queues one connection more than the backlog
+        let _c1 = or_panic!(connect());
+        let _c2 = or_panic!(connect());
+        match connect() {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("unexpected success"),
         }
-    }
 
-    /// Sends data on the socket to the socket's peer.
-    ///
-    /// The peer address may be set by the `connect` method, and this method
-    /// will return an error if the socket has not already been connected.
-    ///
-    /// On success, returns the number of bytes written.
-    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
-        self.inner.send(buf)
+        or_panic!(listener.set_backlog(4));
+        assert_eq!(4, or_panic!(listener.backlog()));
+        let _c3 = or_panic!(connect());
     }
 
-    /// Sets the read timeout for the socket.
-    ///
-    /// If the provided value is `None`, then `recv` and `recv_from` calls will
-    /// block indefinitely. It is an error to pass the zero `Duration` to this
-    /// method.
-    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
-        self.inner.set_timeout(timeout, libc::SO_RCVTIMEO)
-    }
+    #[test]
+    fn read_vectored_filled() {
+        let (mut s1, s2) = or_panic!(UnixStream::pair());
+        or_panic!(s1.write_all(&[1; 20]));
 
-    /// Sets the write timeout for the socket.
-    ///
-    /// If the provided value is `None`, then `send` and `send_to` calls will
-    /// block indefinitely. It is an error to pass the zero `Duration` to this
-    /// method.
-    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
-        self.inner.set_timeout(timeout, libc::SO_SNDTIMEO)
+        let mut buf1 = [0; 4];
+        let mut buf2 = [0; 16];
+        let mut buf3 = [0; 8];
+        let filled = or_panic!(s2.read_vectored_filled(&mut [IoSliceMut::new(&mut buf1),
+                                                             IoSliceMut::new(&mut buf2),
+                                                             IoSliceMut::new(&mut buf3)]));
+        assert_eq!(vec![4, 16, 0], filled);
     }
 
-    /// Returns the read timeout of this socket.
-    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
-        self.inner.timeout(libc::SO_RCVTIMEO)
-    }
+    #[test]
+    fn local_addr_opt() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path = dir.path().join("sock");
 
-    /// Returns the write timeout of this socket.
-    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
-        self.inner.timeout(libc::SO_SNDTIMEO)
-    }
+        let sock = or_panic!(UnixDatagram::unbound());
+        assert!(sock.local_addr_opt().is_none());
+        let sock = or_panic!(UnixDatagram::bind(&path));
+        assert_eq!(Some(&*path), sock.local_addr_opt().unwrap().as_pathname());
 
-    /// Moves the socket into or out of nonblocking mode.
-    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
-        self.inner.set_nonblocking(nonblocking)
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        assert!(s1.local_addr_opt().is_none());
     }
 
-    /// Returns the value of the `SO_ERROR` option.
-    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
-        self.inner.take_error()
-    }
+    #[test]
+    fn connect_with() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+        let listener = or_panic!(UnixListener::bind(&socket_path));
 
-    /// Shut down the read, write, or both halves of this connection.
-    ///
-    /// This function will cause all pending and future I/O calls on the
-    /// specified portions to immediately return with an appropriate value
-    /// (see the documentation of `Shutdown`).
-    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
-        self.inner.shutdown(how)
+        let (default, _) = or_panic!(UnixStream::pair());
+        let cfg = SocketConfig {
+            recv_buffer_size: Some(4096),
+            ..SocketConfig::default()
+        };
+        let stream = or_panic!(UnixStream::connect_with(&socket_path, &cfg));
+        or_panic!(listener.accept());
+        check_buffer_size(4096,
+                          or_panic!(stream.recv_buffer_size()),
+                          or_panic!(default.recv_buffer_size()));
     }
-}
 
-impl AsRawFd for UnixDatagram {
-    fn as_raw_fd(&self) -> RawFd {
-        self.inner.0
-    }
-}
+    #[test]
+    fn incoming_accept_template() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
 
-impl FromRawFd for UnixDatagram {
-    unsafe fn from_raw_fd(fd: RawFd) -> UnixDatagram {
-        UnixDatagram { inner: Inner(fd) }
-    }
-}
+        let mut listener = or_panic!(UnixListener::bind(&socket_path));
+        listener.set_accept_template(SocketConfig {
+            read_timeout: Some(Duration::from_secs(1)),
+            ..SocketConfig::default()
+        });
 
-impl IntoRawFd for UnixDatagram {
-    fn into_raw_fd(self) -> RawFd {
-        let fd = self.inner.0;
-        mem::forget(self);
-        fd
+        let _client = or_panic!(UnixStream::connect(&socket_path));
+        let stream = or_panic!(listener.incoming().next().unwrap());
+        assert_eq!(Some(Duration::from_secs(1)), or_panic!(stream.read_timeout()));
     }
-}
 
-/// A Unix seqpacket socket.
-///
-/// A Unix Seqpacket socket is connection oriented but sends and receives
-/// datagrams with guaranteed ordering.
-///
-/// # Examples
-///
-/// ```rust,no_run
-/// use unix_socket::UnixSeqpacket;
-///
-/// let path = "/path/to/my/socket";
-/// let socket = UnixSeqpacket::connect(path).unwrap();
-/// let _count = socket.send(b"hello world").unwrap();
-/// let mut buf = [0; 100];
-/// let count = socket.recv(&mut buf).unwrap();
-/// println!("socket {:?} sent {:?}", path, &buf[..count]);
-/// ```
-pub struct UnixSeqpacket {
-    inner: Inner,
-}
+    #[test]
+    fn send_fd_batch() {
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        let socks = (0..10).map(|_| or_panic!(UnixDatagram::unbound())).collect::<Vec<_>>();
+        let fds = socks.iter().map(|s| s.as_raw_fd()).collect::<Vec<_>>();
 
-impl fmt::Debug for UnixSeqpacket {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let mut builder = fmt.debug_struct("UnixSeqpacket");
-        builder.field("fd", &self.inner.0);
-        if let Ok(addr) = self.local_addr() {
-            builder.field("local", &addr);
-        }
-        if let Ok(addr) = self.peer_addr() {
-            builder.field("peer", &addr);
+        or_panic!(s1.send_fd_batch(&fds));
+        let mut buf = [0; 10];
+        let (len, received) = or_panic!(s2.recv_fds(&mut buf));
+        assert_eq!(1, len);
+        assert_eq!(10, received.len());
+        for fd in received {
+            let sock = unsafe { UnixDatagram::from_raw_fd(fd) };
+            assert!(!fds.contains(&sock.as_raw_fd()));
         }
-        builder.finish()
     }
-}
 
-impl UnixSeqpacket {
-    /// Connects to the socket named by `path`.
-    ///
-    /// Linux provides, as a nonportable extension, a separate "abstract"
-    /// address namespace as opposed to filesystem-based addressing. If `path`
-    /// begins with a null byte, it will be interpreted as an "abstract"
-    /// address. Otherwise, it will be interpreted as a "pathname" address,
-    /// corresponding to a path on the filesystem.
-    pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<UnixSeqpacket> {
-        unsafe {
-            let inner = try!(Inner::new(libc::SOCK_SEQPACKET));
-            let (addr, len) = try!(sockaddr_un(path));
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn debug_accepting() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
 
-            let ret = libc::connect(inner.0, &addr as *const _ as *const _, len);
-            if ret < 0 {
-                Err(io::Error::last_os_error())
-            } else {
-                Ok(UnixSeqpacket { inner: inner })
-            }
-        }
+        let listener = or_panic!(UnixListener::bind(&socket_path));
+        let stream = or_panic!(UnixStream::connect(&socket_path));
+        assert!(format!("{:?}", listener).contains("accepting: true"));
+        assert!(format!("{:?}", stream).contains("accepting: false"));
     }
 
-    /// Create an unnamed pair of connected sockets.
-    ///
-    /// Returns two `UnixSeqpackets`s which are connected to each other.
-    pub fn pair() -> io::Result<(UnixSeqpacket, UnixSeqpacket)> {
-        let (i1, i2) = try!(Inner::new_pair(libc::SOCK_SEQPACKET));
-        Ok((UnixSeqpacket { inner: i1 }, UnixSeqpacket { inner: i2 }))
-    }
+    #[test]
+    fn recv_deadline() {
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        let mut buf = [0; 10];
 
-    /// Creates a new independently owned handle to the underlying socket.
-    ///
-    /// The returned `UnixListener` is a reference to the same socket that this
-    /// object references. Both handles can be used to accept incoming
-    /// connections and options set on one listener will affect the other.
-    pub fn try_clone(&self) -> io::Result<UnixSeqpacket> {
-        Ok(UnixSeqpacket { inner: try!(self.inner.try_clone()) })
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(50);
+        let kind = s1.recv_deadline(&mut buf, deadline).err().expect("expected error").kind();
+        assert_eq!(io::ErrorKind::TimedOut, kind);
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(50));
+        assert!(elapsed < Duration::from_secs(1));
+
+        or_panic!((&s2).write_all(b"hello"));
+        let deadline = Instant::now() + Duration::from_secs(1);
+        assert_eq!(5, or_panic!(s1.recv_deadline(&mut buf, deadline)));
+        assert_eq!(b"hello", &buf[..5]);
     }
 
-    /// Returns the address of this socket.
-    pub fn local_addr(&self) -> io::Result<SocketAddr> {
-        SocketAddr::new(|addr, len| unsafe { libc::getsockname(self.inner.0, addr, len) })
+    #[test]
+    fn datagram_recv_buf_from() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path1 = dir.path().join("sock1");
+        let path2 = dir.path().join("sock2");
+
+        let sock1 = or_panic!(UnixDatagram::bind(&path1));
+        let sock2 = or_panic!(UnixDatagram::bind(&path2));
+        or_panic!(sock1.send_to(b"hello world", &path2));
+
+        let mut buf = [MaybeUninit::<u8>::uninit(); 64];
+        let (len, addr) = or_panic!(sock2.recv_buf_from(&mut buf));
+        assert_eq!(11, len);
+        assert_eq!(Some(&*path1), addr.as_pathname());
+        let data = unsafe { slice::from_raw_parts(buf.as_ptr() as *const u8, len) };
+        assert_eq!(b"hello world", data);
     }
 
-    /// Returns the address of this socket's peer.
-    ///
-    /// Returns the SocketAddr (path) of the peer of this connected socket
-    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
-        SocketAddr::new(|addr, len| unsafe { libc::getpeername(self.inner.0, addr, len) })
+    #[test]
+    fn reset() {
+        let (mut s1, s2) = or_panic!(UnixStream::pair());
+        or_panic!(s1.write_all(b"unread"));
+        or_panic!(s2.reset());
+
+        let mut buf = [0; 10];
+        let kind = s1.read(&mut buf).err().expect("expected error").kind();
+        assert_eq!(io::ErrorKind::ConnectionReset, kind);
     }
 
-    /// Receives data from the socket from the connected peer.
-    ///
-    /// On success, returns the number of bytes read.
-    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner.recv(buf)
+    #[test]
+    fn timeout_round_trip() {
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+
+        or_panic!(s1.set_read_timeout(Some(Duration::new(1, 500_000_000))));
+        assert_eq!(Some(Duration::new(1, 500_000_000)), or_panic!(s1.read_timeout()));
+
+        // Anything finer than a microsecond is truncated
+        or_panic!(s1.set_write_timeout(Some(Duration::new(1, 500_000_999))));
+        assert_eq!(Some(Duration::new(1, 500_000_000)), or_panic!(s1.write_timeout()));
     }
 
-    /// Sends data on the socket to the socket's peer.
-    ///
-    /// will return an error if the socket has not already been connected.
-    ///
-    /// On success, returns the number of bytes written.
-    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
-        self.inner.send(buf)
+    #[test]
+    fn framed_round_trip() {
+        use framed::{FramedStream, LengthPrefix};
+
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        let mut s1 = FramedStream::new(s1);
+        let mut s2 = FramedStream::new(s2);
+
+        or_panic!(s1.send_frame(b"hello"));
+        or_panic!(s1.send_frame(b""));
+        assert_eq!(b"hello".to_vec(), or_panic!(s2.recv_frame()));
+        assert_eq!(Vec::<u8>::new(), or_panic!(s2.recv_frame()));
+
+        s1.set_length_prefix(LengthPrefix::U16LittleEndian);
+        s2.set_length_prefix(LengthPrefix::U16LittleEndian);
+        or_panic!(s1.send_frame(&[7; 300]));
+        assert_eq!(vec![7; 300], or_panic!(s2.recv_frame()));
+
+        let mut header = [0; 2];
+        or_panic!(s1.send_frame(&[1; 258]));
+        or_panic!(s2.get_ref().read_exact(&mut header));
+        assert_eq!([2, 1], header);
     }
 
-    /// Sets the read timeout for the socket.
-    ///
-    /// If the provided value is `None`, then `recv` and `recv_from` calls will
-    /// block indefinitely. It is an error to pass the zero `Duration` to this
-    /// method.
-    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
-        self.inner.set_timeout(timeout, libc::SO_RCVTIMEO)
+    #[test]
+    fn framed_too_large() {
+        use framed::FramedStream;
+
+        let (mut s1, s2) = or_panic!(UnixStream::pair());
+        let mut s2 = FramedStream::new(s2);
+        s2.set_max_frame_size(1024);
+
+        or_panic!(s1.write_all(&[0xff, 0xff, 0xff, 0xff]));
+        let kind = s2.recv_frame().err().expect("expected error").kind();
+        assert_eq!(io::ErrorKind::InvalidData, kind);
+
+        let kind = s2.send_frame(&[0; 1025]).err().expect("expected error").kind();
+        assert_eq!(io::ErrorKind::InvalidInput, kind);
     }
 
-    /// Sets the write timeout for the socket.
-    ///
-    /// If the provided value is `None`, then `send` and `send_to` calls will
-    /// block indefinitely. It is an error to pass the zero `Duration` to this
-    /// method.
-    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
-        self.inner.set_timeout(timeout, libc::SO_SNDTIMEO)
+    #[test]
+    fn seqpacket_recv_with_eof() {
+        let (s1, s2) = or_panic!(UnixSeqpacket::pair());
+        let mut buf = [0; 10];
+
+        #[cfg(target_os = "linux")]
+        {
+            use os::linux::UnixSocketExt;
+
+            let err = s2.recv_with_eof(&mut buf).err().unwrap();
+            assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+            or_panic!(s2.set_passcred(true));
+        }
+
+        or_panic!(s1.send(b"hi"));
+        or_panic!(s1.send(b""));
+        or_panic!(s1.shutdown(Shutdown::Write));
+
+        assert_eq!(Some(2), or_panic!(s2.recv_with_eof(&mut buf)));
+        assert_eq!(Some(0), or_panic!(s2.recv_with_eof(&mut buf)));
+        assert_eq!(None, or_panic!(s2.recv_with_eof(&mut buf)));
     }
 
-    /// Returns the read timeout of this socket.
-    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
-        self.inner.timeout(libc::SO_RCVTIMEO)
+    #[test]
+    fn datagram_bind_cleanup() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path = dir.path().join("sock");
+
+        let sock = or_panic!(UnixDatagram::bind_cleanup(&path));
+        let clone = or_panic!(sock.try_clone());
+        drop(clone);
+        assert!(path.exists());
+        drop(sock);
+        assert!(!path.exists());
+
+        let mut sock = or_panic!(UnixDatagram::bind_cleanup(&path));
+        sock.forget_unlink();
+        drop(sock);
+        assert!(path.exists());
     }
 
-    /// Returns the write timeout of this socket.
-    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
-        self.inner.timeout(libc::SO_SNDTIMEO)
+    #[test]
+    fn swap_nonblocking() {
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        assert!(!or_panic!(s1.swap_nonblocking(true)));
+        assert!(or_panic!(s1.inner.nonblocking()));
+        assert!(or_panic!(s1.swap_nonblocking(true)));
+        assert!(or_panic!(s1.swap_nonblocking(false)));
+        assert!(!or_panic!(s1.inner.nonblocking()));
     }
 
-    /// Moves the socket into or out of nonblocking mode.
-    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
-        self.inner.set_nonblocking(nonblocking)
+    #[test]
+    fn read_until() {
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        or_panic!((&s1).write_all(b"line1\nline2\n"));
+
+        let mut out = vec![];
+        assert_eq!(6, or_panic!(s2.read_until(b'\n', &mut out)));
+        assert_eq!(&b"line1\n"[..], &out[..]);
+
+        out.clear();
+        drop(s1);
+        assert_eq!(6, or_panic!(s2.read_until(b'\n', &mut out)));
+        assert_eq!(&b"line2\n"[..], &out[..]);
+        assert_eq!(0, or_panic!(s2.read_until(b'\n', &mut out)));
     }
 
-    /// Returns the value of the `SO_ERROR` option.
-    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
-        self.inner.take_error()
+    #[test]
+    fn is_pair_end() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        assert!(s1.is_pair_end());
+        assert!(s2.is_pair_end());
+
+        let listener = or_panic!(UnixListener::bind(&socket_path));
+        let stream = or_panic!(UnixStream::connect(&socket_path));
+        let (accepted, _) = or_panic!(listener.accept());
+        assert!(!stream.is_pair_end());
+        assert!(!accepted.is_pair_end());
+
+        let (s1, _s2) = or_panic!(UnixSeqpacket::pair());
+        assert!(s1.is_pair_end());
     }
 
-    /// Shut down the read, write, or both halves of this connection.
-    ///
-    /// This function will cause all pending and future I/O calls on the
-    /// specified portions to immediately return with an appropriate value
-    /// (see the documentation of `Shutdown`).
-    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
-        self.inner.shutdown(how)
+    #[test]
+    fn keepalive() {
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        assert!(!or_panic!(s1.keepalive()));
+        or_panic!(s1.set_keepalive(true));
+        assert!(or_panic!(s1.keepalive()));
+        or_panic!(s1.set_keepalive(false));
+        assert!(!or_panic!(s1.keepalive()));
+
+        let (d1, _d2) = or_panic!(UnixDatagram::pair());
+        or_panic!(d1.set_keepalive(true));
+        assert!(or_panic!(d1.keepalive()));
+
+        let (p1, _p2) = or_panic!(UnixSeqpacket::pair());
+        or_panic!(p1.set_keepalive(true));
+        assert!(or_panic!(p1.keepalive()));
     }
-}
 
-impl AsRawFd for UnixSeqpacket {
-    fn as_raw_fd(&self) -> RawFd {
-        self.inner.0
+    #[test]
+    fn recv_stream() {
+        let (tx, rx) = or_panic!(UnixStream::pair());
+        let (mut s1, s2) = or_panic!(UnixStream::pair());
+
+        or_panic!(tx.send_fd_batch(&[s2.as_raw_fd()]));
+        drop(s2);
+        let mut s2 = or_panic!(rx.recv_stream()).unwrap();
+        or_panic!(s1.write_all(b"hello"));
+        let mut buf = [0; 5];
+        or_panic!(s2.read_exact(&mut buf));
+        assert_eq!(b"hello", &buf);
+
+        or_panic!((&tx).write_all(b"x"));
+        assert!(or_panic!(rx.recv_stream()).is_none());
+
+        let (d1, _d2) = or_panic!(UnixDatagram::pair());
+        or_panic!(tx.send_fd_batch(&[d1.as_raw_fd()]));
+        let err = rx.recv_stream().err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
     }
-}
 
-impl FromRawFd for UnixSeqpacket {
-    unsafe fn from_raw_fd(fd: RawFd) -> UnixSeqpacket {
-        UnixSeqpacket { inner: Inner(fd) }
+    #[test]
+    fn into_shared() {
+        let (mut s1, s2) = or_panic!(UnixStream::pair());
+        let shared = s2.into_shared();
+        let clone = shared.clone();
+        assert_eq!(shared.as_raw_fd(), clone.as_raw_fd());
+
+        let thread = thread::spawn(move || {
+            let mut buf = [0; 2];
+            or_panic!((&clone).read_exact(&mut buf));
+            or_panic!((&clone).write_all(&buf));
+        });
+
+        or_panic!(s1.write_all(b"ab"));
+        let mut buf = [0; 2];
+        or_panic!(s1.read_exact(&mut buf));
+        assert_eq!(b"ab", &buf);
+        thread.join().unwrap();
+
+        or_panic!(s1.write_all(b"cd"));
+        or_panic!((&shared).read_exact(&mut buf));
+        assert_eq!(b"cd", &buf);
     }
-}
 
-impl IntoRawFd for UnixSeqpacket {
-    fn into_raw_fd(self) -> RawFd {
-        let fd = self.inner.0;
-        mem::forget(self);
-        fd
+    #[test]
+    fn file_ident() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path1 = dir.path().join("sock1");
+        let path2 = dir.path().join("sock2");
+        let link = dir.path().join("link");
+
+        let listener1 = or_panic!(UnixListener::bind(&path1));
+        let listener2 = or_panic!(UnixListener::bind(&path2));
+        or_panic!(fs::hard_link(&path1, &link));
+
+        let ident = or_panic!(listener1.file_ident());
+        let metadata = or_panic!(fs::metadata(&link));
+        assert_eq!((metadata.dev(), metadata.ino()), ident);
+        assert!(ident != or_panic!(listener2.file_ident()));
     }
-}
 
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn file_ident_abstract() {
+        let listener = or_panic!(UnixListener::bind("\0file_ident_abstract"));
+        let err = listener.file_ident().err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+    }
 
-#[cfg(test)]
-mod test {
-    extern crate tempdir;
+    #[test]
+    fn recv_timed_partial() {
+        let (mut s1, s2) = or_panic!(UnixStream::pair());
+        or_panic!(s1.write_all(b"hello"));
 
-    use std::thread;
-    use std::io;
-    use std::io::prelude::*;
-    use std::time::Duration;
-    use self::tempdir::TempDir;
-    use std::net::Shutdown;
+        let mut buf = [0; 10];
+        let start = Instant::now();
+        assert_eq!(5, or_panic!(s2.recv_timed_partial(&mut buf, Duration::from_millis(100))));
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(b"hello", &buf[..5]);
 
-    use super::*;
+        assert_eq!(0, or_panic!(s2.recv_timed_partial(&mut buf, Duration::from_millis(10))));
 
-    macro_rules! or_panic {
-        ($e:expr) => {
-            match $e {
-                Ok(e) => e,
-                Err(e) => panic!("{}", e),
-            }
-        }
+        or_panic!(s1.write_all(b"0123456789"));
+        assert_eq!(10, or_panic!(s2.recv_timed_partial(&mut buf, Duration::from_secs(10))));
+        assert_eq!(b"0123456789", &buf);
     }
 
     #[test]
-    fn basic_stream() {
+    fn from_inherited_fd() {
         let dir = or_panic!(TempDir::new("unix_socket"));
         let socket_path = dir.path().join("sock");
-        let msg1 = b"hello";
-        let msg2 = b"world!";
 
         let listener = or_panic!(UnixListener::bind(&socket_path));
-        let thread = thread::spawn(move || {
-            let mut stream = or_panic!(listener.accept()).0;
-            let mut buf = [0; 5];
-            or_panic!(stream.read(&mut buf));
-            assert_eq!(&msg1[..], &buf[..]);
-            or_panic!(stream.write_all(msg2));
-        });
-
-        let mut stream = or_panic!(UnixStream::connect(&socket_path));
+        let listener = or_panic!(unsafe { UnixListener::from_inherited_fd(listener.into_raw_fd()) });
         assert_eq!(Some(&*socket_path),
-                   stream.peer_addr().unwrap().as_pathname());
-        or_panic!(stream.write_all(msg1));
-        let mut buf = vec![];
-        or_panic!(stream.read_to_end(&mut buf));
-        assert_eq!(&msg2[..], &buf[..]);
-        drop(stream);
+                   or_panic!(listener.local_addr()).as_pathname());
+        let _stream = or_panic!(UnixStream::connect(&socket_path));
+        or_panic!(listener.accept());
 
-        thread.join().unwrap();
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        let err = unsafe { UnixListener::from_inherited_fd(s1.as_raw_fd()) }.err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+
+        let (d1, _d2) = or_panic!(UnixDatagram::pair());
+        let err = unsafe { UnixListener::from_inherited_fd(d1.as_raw_fd()) }.err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
     }
 
     #[test]
-    fn basic_seqpacket() {
+    #[cfg(target_os = "linux")]
+    fn recv_errqueue() {
+        use os::linux::UnixSocketExt;
+
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        let mut buf = [0; 16];
+        let mut control = [0; 64];
+        let err = s1.recv_errqueue(&mut buf, &mut control).err().unwrap();
+        assert_eq!(io::ErrorKind::WouldBlock, err.kind());
+
+        let (d1, _d2) = or_panic!(UnixDatagram::pair());
+        or_panic!(d1.set_read_timeout(Some(Duration::from_secs(1))));
+        let err = d1.recv_errqueue(&mut buf, &mut control).err().unwrap();
+        assert_eq!(io::ErrorKind::WouldBlock, err.kind());
+    }
+
+    #[test]
+    fn bind_nonblocking() {
         let dir = or_panic!(TempDir::new("unix_socket"));
         let socket_path = dir.path().join("sock");
-        let msg1 = b"hello";
-        let msg2 = b"world!";
 
-        let listener = or_panic!(UnixSeqpacketListener::bind(&socket_path));
-        let thread = thread::spawn(move || {
-            let stream = or_panic!(listener.accept()).0;
-            let mut buf = [0; 5];
-            let res = or_panic!(stream.recv(&mut buf));
-            println!("recv in thread result was {}", res);
-            assert_eq!(&msg1[..], &buf[..]);
-            let res = or_panic!(stream.send(msg2));
-            println!("send in thread result was {}", res);
-        });
+        let listener = or_panic!(UnixListener::bind_nonblocking(&socket_path));
+        assert!(or_panic!(listener.inner.nonblocking()));
+        assert!(or_panic!(listener.fd_flags()) & libc::FD_CLOEXEC != 0);
+        let err = listener.accept().err().unwrap();
+        assert_eq!(io::ErrorKind::WouldBlock, err.kind());
 
-        let stream = or_panic!(UnixSeqpacket::connect(&socket_path));
-        assert_eq!(Some(&*socket_path),
-                   stream.peer_addr().unwrap().as_pathname());
-        let res = or_panic!(stream.send(msg1));
-        println!("outer send result was {}", res);
-        let mut buf = vec![0,0,0,0,0,0];
-        let res = or_panic!(stream.recv(&mut buf));
-        println!("outer recv result was {}", res);
-        assert_eq!(&msg2[..], &buf[..]);
-        drop(stream);
+        let _stream = or_panic!(UnixStream::connect(&socket_path));
+        or_panic!(listener.accept());
+    }
 
-        thread.join().unwrap();
+    #[test]
+    fn unlink_on_drop_try_clone() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+
+        let mut listener = or_panic!(UnixListener::bind(&socket_path));
+        or_panic!(listener.set_unlink_on_drop(true));
+        let clone = or_panic!(listener.try_clone());
+        drop(clone);
+        assert!(socket_path.exists());
+        drop(listener);
+        assert!(!socket_path.exists());
+
+        let mut listener = or_panic!(UnixSeqpacketListener::bind(&socket_path));
+        or_panic!(listener.set_unlink_on_drop(true));
+        let clone = or_panic!(listener.try_clone());
+        drop(clone);
+        assert!(socket_path.exists());
+        drop(listener);
+        assert!(!socket_path.exists());
     }
 
+    #[test]
+    fn recv_from_full_addr() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path1 = dir.path().join("sock1");
+        let path2 = dir.path().join("sock2");
+
+        let sock1 = or_panic!(UnixDatagram::bind(&path1));
+        let sock2 = or_panic!(UnixDatagram::bind(&path2));
+
+        for _ in 0..2 {
+            or_panic!(sock1.send_to(b"hello", &path2));
+            let (buf, sender, local) = or_panic!(sock2.recv_from_full_addr());
+            assert_eq!(&b"hello"[..], &buf[..]);
+            assert_eq!(Some(&*path1), sender.as_pathname());
+            assert_eq!(or_panic!(sock2.local_addr()).as_pathname(),
+                       local.as_pathname());
+            assert_eq!(Some(&*path2), local.as_pathname());
+        }
+    }
 
     #[test]
-    fn pair() {
-        let msg1 = b"hello";
-        let msg2 = b"world!";
+    fn buffered_stream() {
+        let (mut s1, s2) = or_panic!(UnixStream::pair());
+        let mut buffered = BufferedUnixStream::with_capacity(64, s2);
+        let data = (0..100).collect::<Vec<u8>>();
+        or_panic!(s1.write_all(&data));
+
+        let mut byte = [0];
+        or_panic!(buffered.read_exact(&mut byte));
+        assert_eq!(0, byte[0]);
+        // The first read pulled in a whole buffer's worth
+        assert_eq!(63, buffered.buffer().len());
+        for i in 1..64 {
+            or_panic!(buffered.read_exact(&mut byte));
+            assert_eq!(i, byte[0]);
+        }
+        assert!(buffered.buffer().is_empty());
 
-        let (mut s1, mut s2) = or_panic!(UnixStream::pair());
-        let thread = thread::spawn(move || {
-            // s1 must be moved in or the test will hang!
-            let mut buf = [0; 5];
-            or_panic!(s1.read(&mut buf));
-            assert_eq!(&msg1[..], &buf[..]);
-            or_panic!(s1.write_all(msg2));
-        });
+        let mut line = vec![];
+        or_panic!(s1.write_all(b"abc\n"));
+        or_panic!(buffered.read_until(b'\n', &mut line));
+        assert_eq!(&data[64..], &line[..36]);
+        assert_eq!(&b"abc\n"[..], &line[36..]);
 
-        or_panic!(s2.write_all(msg1));
-        let mut buf = vec![];
-        or_panic!(s2.read_to_end(&mut buf));
-        assert_eq!(&msg2[..], &buf[..]);
-        drop(s2);
+        or_panic!(buffered.write_all(b"reply"));
+        let mut buf = [0; 5];
+        or_panic!(s1.read_exact(&mut buf));
+        assert_eq!(b"reply", &buf);
+        assert_eq!(buffered.as_raw_fd(), buffered.into_inner().as_raw_fd());
+    }
 
-        thread.join().unwrap();
+    #[test]
+    fn bind_atomic() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("sock");
+
+        let old = or_panic!(UnixListener::bind(&socket_path));
+        let mut old_client = or_panic!(UnixStream::connect(&socket_path));
+        let (mut old_conn, _) = or_panic!(old.accept());
+
+        let new = or_panic!(UnixListener::bind_atomic(&socket_path));
+        assert_eq!(1, or_panic!(fs::read_dir(dir.path())).count());
+        or_panic!(new.set_nonblocking(true));
+        or_panic!(old.set_nonblocking(true));
+
+        let _client = or_panic!(UnixStream::connect(&socket_path));
+        or_panic!(new.accept());
+        let err = old.accept().err().unwrap();
+        assert_eq!(io::ErrorKind::WouldBlock, err.kind());
+
+        or_panic!(old_client.write_all(b"x"));
+        let mut buf = [0];
+        or_panic!(old_conn.read_exact(&mut buf));
+
+        assert_eq!(Some(&*socket_path), or_panic!(new.local_addr()).as_pathname());
+        let meta = or_panic!(fs::metadata(&socket_path));
+        assert_eq!((meta.dev(), meta.ino()), or_panic!(new.file_ident()));
+        let mut new = new;
+        or_panic!(new.set_unlink_on_drop(true));
+        drop(new);
+        assert!(!socket_path.exists());
+
+        let file_path = dir.path().join("file");
+        or_panic!(fs::write(&file_path, b"data"));
+        match UnixListener::bind_atomic(&file_path) {
+            Err(ref e) if e.kind() == io::ErrorKind::AddrInUse => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(or_panic!(fs::read(&file_path)), b"data");
     }
 
     #[test]
     #[cfg(target_os = "linux")]
-    fn abstract_address() {
-        use os::linux::SocketAddrExt;
+    fn recv_buffer_limits() {
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        let (_, max) = or_panic!(s1.recv_buffer_limits());
+        or_panic!(s1.set_recv_buffer_size(cmp::min(max.saturating_mul(4), libc::c_int::MAX as usize)));
+        let (current, max) = or_panic!(s1.recv_buffer_limits());
+        assert!(current <= max * 2);
+        assert_eq!(current, or_panic!(s1.recv_buffer_size()));
 
-        let socket_path = "\0the path";
-        let msg1 = b"hello";
-        let msg2 = b"world!";
+        let (d1, _d2) = or_panic!(UnixDatagram::pair());
+        or_panic!(d1.recv_buffer_limits());
+        let (p1, _p2) = or_panic!(UnixSeqpacket::pair());
+        or_panic!(p1.recv_buffer_limits());
+    }
 
-        let listener = or_panic!(UnixListener::bind(&socket_path));
-        let thread = thread::spawn(move || {
-            let mut stream = or_panic!(listener.accept()).0;
-            let mut buf = [0; 5];
-            or_panic!(stream.read(&mut buf));
-            assert_eq!(&msg1[..], &buf[..]);
-            or_panic!(stream.write_all(msg2));
+    #[test]
+    fn graceful_close() {
+        let (s1, mut s2) = or_panic!(UnixStream::pair());
+        or_panic!(s1.set_send_buffer_size(4096));
+        let data = vec![1; 64 * 1024];
+        let writer = or_panic!(s1.try_clone());
+
+        let thread = thread::spawn(move || {
+            let mut received = 0;
+            let mut buf = [0; 1024];
+            loop {
+                thread::sleep(Duration::from_millis(1));
+                match or_panic!(s2.read(&mut buf)) {
+                    0 => break,
+                    n => received += n,
+                }
+            }
+            or_panic!(s2.write_all(b"bye"));
+            received
         });
 
-        let mut stream = or_panic!(UnixStream::connect(&socket_path));
-        assert_eq!(Some(&b"the path"[..]),
-                   stream.peer_addr().unwrap().as_abstract());
-        or_panic!(stream.write_all(msg1));
-        let mut buf = vec![];
-        or_panic!(stream.read_to_end(&mut buf));
-        assert_eq!(&msg2[..], &buf[..]);
-        drop(stream);
+        or_panic!((&writer).write_all(&data));
+        drop(writer);
+        or_panic!(s1.graceful_close(Duration::from_secs(10)));
+        assert_eq!(data.len(), thread.join().unwrap());
 
-        thread.join().unwrap();
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        let err = s1.graceful_close(Duration::from_millis(50)).err().unwrap();
+        assert_eq!(io::ErrorKind::TimedOut, err.kind());
     }
 
     #[test]
-    fn try_clone() {
+    fn same_socket() {
         let dir = or_panic!(TempDir::new("unix_socket"));
         let socket_path = dir.path().join("sock");
-        let msg1 = b"hello";
-        let msg2 = b"world";
 
         let listener = or_panic!(UnixListener::bind(&socket_path));
-        let thread = thread::spawn(move || {
-            let mut stream = or_panic!(listener.accept()).0;
-            or_panic!(stream.write_all(msg1));
-            or_panic!(stream.write_all(msg2));
-        });
+        let s1 = or_panic!(UnixStream::connect(&socket_path));
+        let s2 = or_panic!(UnixStream::connect(&socket_path));
+        let clone = or_panic!(s1.try_clone());
+        assert!(or_panic!(s1.same_socket(&clone)));
+        assert!(!or_panic!(s1.same_socket(&s2)));
+        assert!(or_panic!(listener.same_socket(&or_panic!(listener.try_clone()))));
 
-        let mut stream = or_panic!(UnixStream::connect(&socket_path));
-        let mut stream2 = or_panic!(stream.try_clone());
+        let (d1, d2) = or_panic!(UnixDatagram::pair());
+        assert!(!or_panic!(d1.same_socket(&d2)));
+        assert!(or_panic!(d1.same_socket(&or_panic!(d1.try_clone()))));
 
-        let mut buf = [0; 5];
-        or_panic!(stream.read(&mut buf));
-        assert_eq!(&msg1[..], &buf[..]);
-        or_panic!(stream2.read(&mut buf));
-        assert_eq!(&msg2[..], &buf[..]);
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        assert!(or_panic!(s1.inner.same_inode(&or_panic!(s1.try_clone()).inner)));
+        assert!(!or_panic!(s1.inner.same_inode(&s2.inner)));
+    }
 
-        thread.join().unwrap();
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn recv_from_creds() {
+        use os::linux::{UnixDatagramExt, UnixSocketExt};
+
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path1 = dir.path().join("sock1");
+        let path2 = dir.path().join("sock2");
+
+        let sock1 = or_panic!(UnixDatagram::bind(&path1));
+        let sock2 = or_panic!(UnixDatagram::bind(&path2));
+        let mut buf = [0; 16];
+        let mut control = [0; 64];
+
+        or_panic!(sock1.send_to(b"hello", &path2));
+        let (count, addr, cred) = or_panic!(sock2.recv_from_creds(&mut buf, &mut control));
+        assert_eq!(&b"hello"[..], &buf[..count]);
+        assert_eq!(Some(&*path1), addr.as_pathname());
+        assert!(cred.is_none());
+
+        or_panic!(sock2.set_passcred(true));
+        or_panic!(sock1.send_to(b"world", &path2));
+        let (count, addr, cred) = or_panic!(sock2.recv_from_creds(&mut buf, &mut control));
+        assert_eq!(&b"world"[..], &buf[..count]);
+        assert_eq!(Some(&*path1), addr.as_pathname());
+        let cred = cred.unwrap();
+        assert_eq!(unsafe { libc::getuid() }, cred.uid);
+        assert_eq!(unsafe { libc::getpid() }, cred.pid);
     }
 
     #[test]
-    fn iter() {
+    fn bind_and_connect() {
         let dir = or_panic!(TempDir::new("unix_socket"));
-        let socket_path = dir.path().join("sock");
+        let local_path = dir.path().join("local");
+        let peer_path = dir.path().join("peer");
 
-        let listener = or_panic!(UnixListener::bind(&socket_path));
-        let thread = thread::spawn(move || {
-            for stream in listener.incoming().take(2) {
-                let mut stream = or_panic!(stream);
-                let mut buf = [0];
-                or_panic!(stream.read(&mut buf));
-            }
-        });
+        let peer = or_panic!(UnixDatagram::bind(&peer_path));
+        let sock = or_panic!(UnixDatagram::bind_and_connect(&local_path, &peer_path));
+        or_panic!(sock.send(b"hello"));
 
-        for _ in 0..2 {
-            let mut stream = or_panic!(UnixStream::connect(&socket_path));
-            or_panic!(stream.write_all(&[0]));
-        }
+        let mut buf = [0; 5];
+        let (count, addr) = or_panic!(peer.recv_from(&mut buf));
+        assert_eq!(&b"hello"[..], &buf[..count]);
+        assert_eq!(Some(&*local_path), addr.as_pathname());
 
-        thread.join().unwrap();
+        or_panic!(peer.send_to(b"world", addr.as_pathname().unwrap()));
+        or_panic!(sock.recv(&mut buf));
+        assert_eq!(b"world", &buf);
+
+        let other_path = dir.path().join("other");
+        assert!(UnixDatagram::bind_and_connect(&other_path, dir.path().join("missing")).is_err());
+        assert!(!other_path.exists());
     }
 
     #[test]
-    fn long_path() {
+    fn connection_id() {
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        let clone = or_panic!(s1.try_clone());
+        assert_eq!(s1.connection_id(), clone.connection_id());
+        assert!(s1.connection_id() != s2.connection_id());
+
+        let id = s1.connection_id();
+        drop(s1);
+        drop(clone);
+        let (s3, _s4) = or_panic!(UnixStream::pair());
+        assert!(id != s3.connection_id());
+    }
+
+    #[test]
+    fn try_send_to() {
         let dir = or_panic!(TempDir::new("unix_socket"));
-        let socket_path = dir.path()
-                             .join("asdfasdfasdfasdfasdfasdfasdfasdfasdfasdfasdfasdfasdfasdfasdfa\
-                                    sasdfasdfasdasdfasdfasdfadfasdfasdfasdfasdfasdf");
-        match UnixStream::connect(&socket_path) {
-            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
-            Err(e) => panic!("unexpected error {}", e),
-            Ok(_) => panic!("unexpected success"),
-        }
+        let path1 = dir.path().join("sock1");
+        let path2 = dir.path().join("sock2");
 
-        match UnixListener::bind(&socket_path) {
-            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
-            Err(e) => panic!("unexpected error {}", e),
-            Ok(_) => panic!("unexpected success"),
-        }
+        let sock1 = or_panic!(UnixDatagram::bind(&path1));
+        let sock2 = or_panic!(UnixDatagram::bind(&path2));
+        assert_eq!(Some(5), or_panic!(sock1.try_send_to(b"hello", &path2)));
 
-        match UnixDatagram::bind(&socket_path) {
-            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => {}
-            Err(e) => panic!("unexpected error {}", e),
-            Ok(_) => panic!("unexpected success"),
+        let mut full = false;
+        for _ in 0..100000 {
+            if or_panic!(sock1.try_send_to(&[0; 256], &path2)).is_none() {
+                full = true;
+                break;
+            }
         }
+        assert!(full);
+
+        let mut buf = [0; 5];
+        or_panic!(sock2.recv(&mut buf));
+        assert_eq!(b"hello", &buf);
     }
 
     #[test]
-    fn timeouts() {
-        let dir = or_panic!(TempDir::new("unix_socket"));
-        let socket_path = dir.path().join("sock");
+    #[cfg(target_os = "linux")]
+    fn peer_groups() {
+        use os::linux::UnixSocketExt;
 
-        let _listener = or_panic!(UnixListener::bind(&socket_path));
+        let gid = unsafe { libc::getegid() };
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        let groups = or_panic!(s1.peer_groups());
+        assert_eq!(gid, groups[0]);
 
-        let stream = or_panic!(UnixStream::connect(&socket_path));
-        let dur = Duration::new(15410, 0);
+        let (d1, _d2) = or_panic!(UnixDatagram::pair());
+        assert!(or_panic!(d1.peer_groups()).contains(&gid));
+    }
 
-        assert_eq!(None, or_panic!(stream.read_timeout()));
+    #[test]
+    fn vectored_sender() {
+        let (s1, mut s2) = or_panic!(UnixStream::pair());
+        let mut sender = VectoredSender::with_capacity(2);
 
-        or_panic!(stream.set_read_timeout(Some(dur)));
-        assert_eq!(Some(dur), or_panic!(stream.read_timeout()));
+        let thread = thread::spawn(move || {
+            let mut buf = vec![0; 100 * 9];
+            or_panic!(s2.read_exact(&mut buf));
+            buf
+        });
 
-        assert_eq!(None, or_panic!(stream.write_timeout()));
+        for i in 0..100u8 {
+            let header = [i];
+            let body = b"body";
+            let trailer = b"end\n";
+            let bufs = [IoSlice::new(&header), IoSlice::new(body), IoSlice::new(trailer)];
+            assert_eq!(9, or_panic!(sender.send_vectored(&s1, &bufs)));
+        }
 
-        or_panic!(stream.set_write_timeout(Some(dur)));
-        assert_eq!(Some(dur), or_panic!(stream.write_timeout()));
+        let buf = thread.join().unwrap();
+        for (i, message) in buf.chunks(9).enumerate() {
+            assert_eq!(i as u8, message[0]);
+            assert_eq!(&b"bodyend\n"[..], &message[1..]);
+        }
+    }
 
-        or_panic!(stream.set_read_timeout(None));
-        assert_eq!(None, or_panic!(stream.read_timeout()));
+    #[test]
+    fn stream_recv_buf() {
+        let (mut s1, s2) = or_panic!(UnixStream::pair());
+        or_panic!(s1.write_all(b"hello"));
 
-        or_panic!(stream.set_write_timeout(None));
-        assert_eq!(None, or_panic!(stream.write_timeout()));
+        let mut buf = [MaybeUninit::<u8>::uninit(); 16];
+        let count = or_panic!(s2.recv_buf(&mut buf));
+        assert_eq!(5, count);
+        let received = unsafe { slice::from_raw_parts(buf.as_ptr() as *const u8, count) };
+        assert_eq!(b"hello", received);
     }
 
     #[test]
-    fn test_read_timeout() {
-        let dir = or_panic!(TempDir::new("unix_socket"));
-        let socket_path = dir.path().join("sock");
+    fn bind_runtime() {
+        use std::os::unix::fs::PermissionsExt;
 
-        let _listener = or_panic!(UnixListener::bind(&socket_path));
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let socket_path = dir.path().join("app/sock");
+
+        let listener = or_panic!(UnixListener::bind_runtime_in(dir.path(), Path::new("app/sock")));
+        assert_eq!(Some(&*socket_path), or_panic!(listener.local_addr()).as_pathname());
+        let mode = or_panic!(fs::metadata(dir.path().join("app"))).mode();
+        assert_eq!(0o700, mode & 0o777);
+        or_panic!(UnixStream::connect(&socket_path));
+
+        drop(listener);
+        assert!(!socket_path.exists());
+
+        let shared = dir.path().join("shared");
+        or_panic!(fs::create_dir(&shared));
+        or_panic!(fs::set_permissions(&shared, fs::Permissions::from_mode(0o777)));
+        match UnixListener::bind_runtime_in(dir.path(), Path::new("shared/sock")) {
+            Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(!shared.join("sock").exists());
+    }
 
-        let mut stream = or_panic!(UnixStream::connect(&socket_path));
-        or_panic!(stream.set_read_timeout(Some(Duration::from_millis(1000))));
+    #[test]
+    fn configure() {
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        let cfg = SocketConfig {
+            read_timeout: Some(Duration::from_secs(1)),
+            nonblocking: Some(true),
+            ..SocketConfig::default()
+        };
+        or_panic!(s1.configure(&cfg));
+        assert_eq!(Some(Duration::from_secs(1)), or_panic!(s1.read_timeout()));
+        assert!(or_panic!(s1.inner.nonblocking()));
+
+        let (d1, _d2) = or_panic!(UnixDatagram::pair());
+        let flags = or_panic!(d1.fd_flags());
+        let send_size = or_panic!(d1.send_buffer_size());
+        let cfg = SocketConfig {
+            send_buffer_size: Some(8192),
+            recv_buffer_size: Some(usize::MAX),
+            cloexec: Some(false),
+            ..SocketConfig::default()
+        };
+        let err = d1.configure(&cfg).err().unwrap();
+        assert_eq!("recv_buffer_size", err.option);
+        assert_eq!(io::ErrorKind::InvalidInput, err.source.kind());
+        assert_eq!(send_size, or_panic!(d1.send_buffer_size()));
+        assert_eq!(flags, or_panic!(d1.fd_flags()));
 
-        let mut buf = [0; 10];
-        let kind = stream.read(&mut buf).err().expect("expected error").kind();
-        assert!(kind == io::ErrorKind::WouldBlock || kind == io::ErrorKind::TimedOut);
+        let err = io::Error::from(err);
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
     }
 
     #[test]
-    fn test_read_with_timeout() {
-        let dir = or_panic!(TempDir::new("unix_socket"));
-        let socket_path = dir.path().join("sock");
+    fn dup_to() {
+        // A descriptor this test owns, which dup_to replaces
+        let target = or_panic!(fs::File::open("/dev/null")).into_raw_fd();
+
+        let (s1, mut s2) = or_panic!(UnixStream::pair());
+        assert_eq!(target, or_panic!(unsafe { s1.dup_to(target) }));
+        assert!(unsafe { s1.dup_to(s1.as_raw_fd()) }.is_err());
+
+        let mut target = unsafe { UnixStream::from_raw_fd(target) };
+        assert!(or_panic!(target.same_socket(&s1)));
+        assert_eq!(0, or_panic!(target.fd_flags()) & libc::FD_CLOEXEC);
+        or_panic!(target.write_all(b"hello"));
+        let mut buf = [0; 5];
+        or_panic!(s2.read_exact(&mut buf));
+        assert_eq!(b"hello", &buf);
+    }
 
-        let listener = or_panic!(UnixListener::bind(&socket_path));
+    #[test]
+    fn read_to_end_capped() {
+        let (mut s1, s2) = or_panic!(UnixStream::pair());
+        or_panic!(s1.write_all(&[1; 100]));
+        drop(s1);
+        let mut out = vec![];
+        assert_eq!(100, or_panic!(s2.read_to_end_capped(100, &mut out)));
+        assert_eq!(vec![1; 100], out);
+
+        let (mut s1, s2) = or_panic!(UnixStream::pair());
+        let thread = thread::spawn(move || {
+            let _ = s1.write_all(&[2; 20000]);
+        });
+        let mut out = vec![];
+        let err = s2.read_to_end_capped(10000, &mut out).err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        assert_eq!(vec![2; 10000], out);
+        drop(s2);
+        thread.join().unwrap();
+    }
 
-        let mut stream = or_panic!(UnixStream::connect(&socket_path));
-        or_panic!(stream.set_read_timeout(Some(Duration::from_millis(1000))));
+    #[test]
+    fn age() {
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        thread::sleep(Duration::from_millis(20));
+        let clone = or_panic!(s1.try_clone());
+        assert!(s1.age() >= Duration::from_millis(20));
+        assert!(clone.age() >= Duration::from_millis(20));
 
-        let mut other_end = or_panic!(listener.accept()).0;
-        or_panic!(other_end.write_all(b"hello world"));
+        let (s3, _s4) = or_panic!(UnixStream::pair());
+        assert!(s3.age() < s1.age());
+    }
 
-        let mut buf = [0; 11];
-        or_panic!(stream.read(&mut buf));
-        assert_eq!(b"hello world", &buf[..]);
+    #[test]
+    fn try_send_poll() {
+        let (s1, mut s2) = or_panic!(UnixStream::pair());
+        let buf = [0; 4096];
+        let mut pending = false;
+        for _ in 0..10000 {
+            match s1.try_send_poll(&buf) {
+                Poll::Ready(res) => { or_panic!(res); }
+                Poll::Pending => {
+                    pending = true;
+                    break;
+                }
+            }
+        }
+        assert!(pending);
+
+        or_panic!(s2.set_nonblocking(true));
+        let mut drained = [0; 4096];
+        loop {
+            match s2.read(&mut drained) {
+                Ok(_) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => panic!("unexpected error {}", e),
+            }
+        }
+        match s1.try_send_poll(b"hello") {
+            Poll::Ready(res) => assert_eq!(5, or_panic!(res)),
+            Poll::Pending => panic!("send still pending after draining"),
+        }
 
-        let kind = stream.read(&mut buf).err().expect("expected error").kind();
-        assert!(kind == io::ErrorKind::WouldBlock || kind == io::ErrorKind::TimedOut);
+        let (d1, _d2) = or_panic!(UnixDatagram::pair());
+        match d1.try_send_poll(b"hello") {
+            Poll::Ready(res) => assert_eq!(5, or_panic!(res)),
+            Poll::Pending => panic!("unexpected pending send"),
+        }
     }
 
     #[test]
-    fn test_unix_datagram() {
+    fn nonblocking_constructors() {
         let dir = or_panic!(TempDir::new("unix_socket"));
-        let path1 = dir.path().join("sock1");
-        let path2 = dir.path().join("sock2");
+        let path = dir.path().join("sock");
+        let seqpacket_path = dir.path().join("seqpacket");
+        let datagram_path = dir.path().join("datagram");
+        let nonblocking = SocketConfig {
+            nonblocking: Some(true),
+            ..SocketConfig::default()
+        };
 
-        let sock1 = or_panic!(UnixDatagram::bind(&path1));
-        let sock2 = or_panic!(UnixDatagram::bind(&path2));
+        let listener = or_panic!(UnixListener::bind(&path));
+        assert!(!or_panic!(listener.nonblocking()));
+        let stream = or_panic!(UnixStream::connect(&path));
+        assert!(!or_panic!(stream.nonblocking()));
+        assert!(!or_panic!(or_panic!(listener.accept()).0.nonblocking()));
+        let stream = or_panic!(UnixStream::connect_inheritable(&path));
+        assert!(!or_panic!(stream.nonblocking()));
+        assert!(!or_panic!(or_panic!(listener.accept()).0.nonblocking()));
+        let stream = or_panic!(UnixStream::connect_with(&path, &nonblocking));
+        assert!(or_panic!(stream.nonblocking()));
+        assert!(!or_panic!(or_panic!(listener.accept()).0.nonblocking()));
+        drop(listener);
+
+        let mut listener = or_panic!(UnixListener::bind_nonblocking(dir.path().join("nb")));
+        assert!(or_panic!(listener.nonblocking()));
+        let _stream = or_panic!(UnixStream::connect(dir.path().join("nb")));
+        assert!(!or_panic!(or_panic!(listener.accept()).0.nonblocking()));
+        let _stream = or_panic!(UnixStream::connect(dir.path().join("nb")));
+        let (fd, _) = or_panic!(listener.accept_raw()).unwrap();
+        assert!(or_panic!(unsafe { UnixStream::from_raw_fd(fd) }.nonblocking()));
+        listener.set_accept_template(nonblocking.clone());
+        let _stream = or_panic!(UnixStream::connect(dir.path().join("nb")));
+        assert!(or_panic!(or_panic!(listener.accept()).0.nonblocking()));
+
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        assert!(!or_panic!(s1.nonblocking()) && !or_panic!(s2.nonblocking()));
+        let (s1, s2) = or_panic!(UnixStream::configured_pair(&nonblocking));
+        assert!(or_panic!(s1.nonblocking()) && or_panic!(s2.nonblocking()));
+        assert!(or_panic!(s1.swap_nonblocking(false)));
+        assert!(!or_panic!(s1.nonblocking()));
+
+        let listener = or_panic!(UnixSeqpacketListener::bind(&seqpacket_path));
+        assert!(!or_panic!(listener.nonblocking()));
+        or_panic!(listener.set_nonblocking(true));
+        let socket = or_panic!(UnixSeqpacket::connect(&seqpacket_path));
+        assert!(!or_panic!(socket.nonblocking()));
+        assert!(!or_panic!(or_panic!(listener.accept()).0.nonblocking()));
+        let (p1, _p2) = or_panic!(UnixSeqpacket::pair());
+        assert!(!or_panic!(p1.nonblocking()));
+
+        let socket = or_panic!(UnixDatagram::bind(&datagram_path));
+        assert!(!or_panic!(socket.nonblocking()));
+        assert!(!or_panic!(or_panic!(UnixDatagram::unbound()).nonblocking()));
+        let (d1, _d2) = or_panic!(UnixDatagram::pair());
+        assert!(!or_panic!(d1.nonblocking()));
+        or_panic!(d1.set_nonblocking(true));
+        assert!(or_panic!(d1.nonblocking()));
+    }
 
-        let msg = b"hello world";
-        or_panic!(sock1.send_to(msg, &path2));
-        let mut buf = [0; 11];
-        or_panic!(sock2.recv_from(&mut buf));
-        assert_eq!(msg, &buf[..]);
+    #[test]
+    fn framed_fds() {
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        let mut tx = framed::FramedStream::new(s1);
+        let mut rx = framed::FramedStream::new(s2);
+        let (mut passed, mut other) = or_panic!(UnixStream::pair());
+
+        or_panic!(tx.send_frame_with_fds(b"hello", &[passed.as_raw_fd()]));
+        or_panic!(tx.send_frame(b"no fds"));
+        or_panic!(tx.send_frame_with_fds(b"", &[]));
+
+        let (buf, fds) = or_panic!(rx.recv_frame_with_fds());
+        assert_eq!(&b"hello"[..], &buf[..]);
+        assert_eq!(1, fds.len());
+        let mut received = unsafe { UnixStream::from_raw_fd(fds[0]) };
+        or_panic!(received.write_all(b"x"));
+        let mut byte = [0];
+        or_panic!(other.read_exact(&mut byte));
+        or_panic!(passed.write_all(b"y"));
+        drop(passed);
+        or_panic!(other.read_exact(&mut byte));
+        assert_eq!(b"y", &byte);
+
+        assert_eq!((b"no fds".to_vec(), vec![]), or_panic!(rx.recv_frame_with_fds()));
+        assert_eq!((vec![], vec![]), or_panic!(rx.recv_frame_with_fds()));
+
+        drop(tx);
+        let err = rx.recv_frame_with_fds().err().unwrap();
+        assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
     }
 
     #[test]
-    fn test_unnamed_unix_datagram() {
+    fn addr_exists() {
         let dir = or_panic!(TempDir::new("unix_socket"));
-        let path1 = dir.path().join("sock1");
+        let socket_path = dir.path().join("sock");
 
-        let sock1 = or_panic!(UnixDatagram::bind(&path1));
-        let sock2 = or_panic!(UnixDatagram::unbound());
+        let listener = or_panic!(UnixListener::bind(&socket_path));
+        let addr = or_panic!(listener.local_addr());
+        assert!(addr.exists());
+        or_panic!(fs::remove_file(&socket_path));
+        assert!(!addr.exists());
 
-        let msg = b"hello world";
-        or_panic!(sock2.send_to(msg, &path1));
-        let mut buf = [0; 11];
-        let (usize, addr) = or_panic!(sock1.recv_from(&mut buf));
-        assert_eq!(usize, 11);
-        assert!(addr.is_unnamed());
-        assert_eq!(msg, &buf[..]);
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        assert!(!or_panic!(s1.local_addr()).exists());
     }
 
     #[test]
-    fn test_connect_unix_datagram() {
-        let dir = or_panic!(TempDir::new("unix_socket"));
-        let path1 = dir.path().join("sock1");
-        let path2 = dir.path().join("sock2");
+    fn set_timeouts() {
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        let read = Some(Duration::from_secs(1));
+        let write = Some(Duration::from_secs(2));
+        or_panic!(s1.set_timeouts(read, write));
+        assert_eq!((read, write), or_panic!(s1.timeouts()));
 
-        let bsock1 = or_panic!(UnixDatagram::bind(&path1));
-        let bsock2 = or_panic!(UnixDatagram::bind(&path2));
-        let sock = or_panic!(UnixDatagram::unbound());
-        or_panic!(sock.connect(&path1));
+        let err = s1.set_timeouts(Some(Duration::from_secs(3)), Some(Duration::from_secs(0)))
+                    .err()
+                    .unwrap();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+        assert_eq!((read, write), or_panic!(s1.timeouts()));
 
-        // Check send()
-        let msg = b"hello there";
-        or_panic!(sock.send(msg));
-        let mut buf = [0; 11];
-        let (usize, addr) = or_panic!(bsock1.recv_from(&mut buf));
-        assert_eq!(usize, 11);
-        assert!(addr.is_unnamed());
-        assert_eq!(msg, &buf[..]);
+        or_panic!(s1.set_timeouts(None, None));
+        assert_eq!((None, None), or_panic!(s1.timeouts()));
 
-        // Changing default socket works too
-        or_panic!(sock.connect(&path2));
-        or_panic!(sock.send(msg));
-        or_panic!(bsock2.recv_from(&mut buf));
+        let (d1, _d2) = or_panic!(UnixDatagram::pair());
+        or_panic!(d1.set_timeouts(read, None));
+        assert_eq!((read, None), or_panic!(d1.timeouts()));
     }
 
     #[test]
-    fn test_unix_datagram_recv() {
+    fn address_pathname() {
         let dir = or_panic!(TempDir::new("unix_socket"));
-        let path1 = dir.path().join("sock1");
-
-        let sock1 = or_panic!(UnixDatagram::bind(&path1));
-        let sock2 = or_panic!(UnixDatagram::unbound());
-        or_panic!(sock2.connect(&path1));
+        let socket_path = dir.path().join("sock");
+        let datagram_path = dir.path().join("datagram");
 
-        let msg = b"hello world";
-        or_panic!(sock2.send(msg));
-        let mut buf = [0; 11];
-        let size = or_panic!(sock1.recv(&mut buf));
-        assert_eq!(size, 11);
-        assert_eq!(msg, &buf[..]);
+        let listener = or_panic!(UnixListener::bind_address(&*socket_path));
+        let mut stream = or_panic!(UnixStream::connect_address(socket_path.clone()));
+        let (mut accepted, _) = or_panic!(listener.accept());
+        or_panic!(stream.write_all(b"hello"));
+        let mut buf = [0; 5];
+        or_panic!(accepted.read_exact(&mut buf));
+        assert_eq!(b"hello", &buf);
+
+        let socket = or_panic!(UnixDatagram::bind_address(Address::Pathname(datagram_path.clone())));
+        assert_eq!(Some(&*datagram_path), or_panic!(socket.local_addr()).as_pathname());
+
+        let err = UnixListener::bind_address(Address::Pathname(PathBuf::from("\0sock"))).err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+        let err = UnixStream::connect_address(Address::Unnamed).err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+
+        let seqpacket_path = dir.path().join("seqpacket");
+        let listener = or_panic!(UnixSeqpacketListener::bind_address(&*seqpacket_path));
+        let client = or_panic!(UnixSeqpacket::connect_address(&*seqpacket_path));
+        let (server, _) = or_panic!(listener.accept());
+        or_panic!(client.send(b"hello"));
+        let mut buf = [0; 10];
+        assert_eq!(5, or_panic!(server.recv(&mut buf)));
+        let err = UnixSeqpacket::connect_address(Address::Unnamed).err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
     }
 
     #[test]
-    fn datagram_pair() {
-        let msg1 = b"hello";
-        let msg2 = b"world!";
-
-        let (s1, s2) = or_panic!(UnixDatagram::pair());
-        let thread = thread::spawn(move || {
-            // s1 must be moved in or the test will hang!
-            let mut buf = [0; 5];
-            or_panic!(s1.recv(&mut buf));
-            assert_eq!(&msg1[..], &buf[..]);
-            or_panic!(s1.send(msg2));
-        });
+    #[cfg(target_os = "linux")]
+    fn address_abstract() {
+        use os::linux::SocketAddrExt;
 
-        or_panic!(s2.send(msg1));
-        let mut buf = [0; 6];
-        or_panic!(s2.recv(&mut buf));
-        assert_eq!(&msg2[..], &buf[..]);
-        drop(s2);
+        let name = b"address_abstract\0with a null".to_vec();
+        let listener = or_panic!(UnixListener::bind_address(Address::Abstract(name.clone())));
+        assert_eq!(Some(&name[..]), or_panic!(listener.local_addr()).as_abstract());
+        let _stream = or_panic!(UnixStream::connect_address(Address::Abstract(name.clone())));
+        or_panic!(listener.accept());
 
-        thread.join().unwrap();
+        let socket = or_panic!(UnixDatagram::bind_address(Address::Unnamed));
+        assert!(or_panic!(socket.local_addr()).as_abstract().is_some());
     }
 
     #[test]
-    fn datagram_shutdown() {
-        let s1 = UnixDatagram::unbound().unwrap();
-        let s2 = s1.try_clone().unwrap();
+    fn datagram_recv_vectored_from_fds() {
+        let dir = or_panic!(TempDir::new("unix_socket"));
+        let path1 = dir.path().join("sock1");
+        let path2 = dir.path().join("sock2");
 
-        let thread = thread::spawn(move || {
-            let mut buf = [0; 1];
-            assert_eq!(0, s1.recv_from(&mut buf).unwrap().0);
-        });
+        let sock1 = or_panic!(UnixDatagram::bind(&path1));
+        let sock2 = or_panic!(UnixDatagram::bind(&path2));
+        or_panic!(sock2.connect(&path1));
 
-        thread::sleep(Duration::from_millis(100));
-        s2.shutdown(Shutdown::Read).unwrap();;
+        let (a, b) = or_panic!(UnixStream::pair());
+        or_panic!(sock2.send_fds(b"hello world", &[a.as_raw_fd()]));
 
-        thread.join().unwrap();
+        let mut buf1 = [0; 5];
+        let mut buf2 = [0; 16];
+        let mut fds = vec![];
+        let (count, addr) = {
+            let mut bufs = [IoSliceMut::new(&mut buf1), IoSliceMut::new(&mut buf2)];
+            or_panic!(sock1.recv_vectored_from_fds(&mut bufs, &mut fds))
+        };
+        assert_eq!(11, count);
+        assert_eq!(b"hello", &buf1);
+        assert_eq!(b" world", &buf2[..6]);
+        assert_eq!(Some(&*path2), addr.as_pathname());
+        assert_eq!(1, fds.len());
+
+        let mut received = unsafe { UnixStream::from_raw_fd(fds[0]) };
+        or_panic!(received.write_all(b"x"));
+        let mut out = [0; 1];
+        or_panic!((&b).read_exact(&mut out));
+        assert_eq!(b"x", &out);
     }
 }